@@ -0,0 +1,247 @@
+//! Снятие сырых данных с термокамеры и датчика положения по TCP
+//!
+//! Команды линии связи: `SET <температура>` → `OK`, `TEMP?` → температура камеры,
+//! `POS?` → `<x> <y>` датчика положения.
+
+pub mod mock;
+
+use std::error::Error;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+pub use crate::link::Link;
+use crate::thermo_model::Direction;
+
+/// Термокамера
+pub struct Chamber(pub Link);
+
+impl Chamber {
+    /// задание уставки
+    pub fn set(&mut self, temp: i32) -> Result<(), Box<dyn Error>> {
+        self.0.query(&format!("SET {temp}"))?;
+        Ok(())
+    }
+
+    /// текущая температура
+    pub fn temp(&mut self) -> Result<f64, Box<dyn Error>> {
+        let reply = self.0.query("TEMP?")?;
+        Ok(reply
+            .parse()
+            .map_err(|_| format!("Invalid chamber temperature `{reply}`"))?)
+    }
+}
+
+/// Датчик положения
+pub struct Sensor(pub Link);
+
+impl Sensor {
+    /// текущее положение по осям x и y
+    pub fn position(&mut self) -> Result<(i32, i32), Box<dyn Error>> {
+        let reply = self.0.query("POS?")?;
+        let invalid = || format!("Invalid sensor position `{reply}`");
+        let mut values = reply.split_whitespace().map(|value| value.parse::<i32>());
+        match (values.next(), values.next(), values.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => Ok((x, y)),
+            _ => Err(invalid())?,
+        }
+    }
+}
+
+/// Параметры снятия данных
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcquisitionOptions {
+    /// допуск установления температуры, °C
+    pub settle_tolerance: f64,
+    /// число показаний подряд в допуске, после которого температура считается установившейся
+    pub settle_readings: usize,
+    /// наибольшее время установления на одной уставке
+    pub settle_timeout: Duration,
+    /// интервал опроса температуры при установлении
+    pub poll_interval: Duration,
+    /// интервал между точками на уставке
+    pub sample_interval: Duration,
+    /// подпись сегментов после направления, например дата: `plus 24.11.2021`
+    pub label: String,
+}
+
+impl Default for AcquisitionOptions {
+    fn default() -> Self {
+        Self {
+            settle_tolerance: 0.5,
+            settle_readings: 5,
+            settle_timeout: Duration::from_secs(2 * 3600),
+            poll_interval: Duration::from_secs(10),
+            sample_interval: Duration::from_secs(60),
+            label: String::default(),
+        }
+    }
+}
+
+/// Снятие данных по уставкам
+pub struct Acquisition {
+    pub chamber: Chamber,
+    pub sensor: Sensor,
+    pub options: AcquisitionOptions,
+}
+
+impl Acquisition {
+    /// проход по уставкам `(температура, число точек)`: установление температуры и запись строк
+    /// `temp;x;y` в `writer`, перед сменой направления — строка-комментарий нового сегмента;
+    /// `progress` получает сообщения о ходе снятия, возвращает число записанных точек
+    pub fn run<W: Write, F: FnMut(&str)>(
+        &mut self,
+        setpoints: &[(i32, usize)],
+        mut writer: W,
+        mut progress: F,
+    ) -> Result<usize, Box<dyn Error>> {
+        let mut previous = self.chamber.temp()?;
+        let mut segment = None;
+        let mut count = 0;
+
+        for (setpoint, points) in setpoints.iter().copied() {
+            let direction = match (setpoint as f64).partial_cmp(&previous) {
+                Some(std::cmp::Ordering::Less) => Direction::Minus,
+                Some(std::cmp::Ordering::Greater) => Direction::Plus,
+                _ => segment.unwrap_or(Direction::Plus),
+            };
+            progress(&format!("setpoint {setpoint}: settling"));
+            let temp = self.settle(setpoint)?;
+            progress(&format!("setpoint {setpoint}: settled at {temp:.2}"));
+
+            for i in 0..points {
+                if i > 0 {
+                    std::thread::sleep(self.options.sample_interval);
+                }
+                let temp = self.chamber.temp()?;
+                let (x, y) = self.sensor.position()?;
+                let mut lines = String::new();
+                if segment != Some(direction) {
+                    let name = if direction == Direction::Minus {
+                        "minus"
+                    } else {
+                        "plus"
+                    };
+                    let comment = format!("{name} {}", self.options.label);
+                    lines += &format!("# {};;\r\n", comment.trim_end());
+                    segment = Some(direction);
+                }
+                let row = format!("{};{x};{y}", temp.round() as i32);
+                lines += &format!("{row}\r\n");
+                // строка пишется сразу, чтобы файл можно было обрабатывать во время снятия
+                writer.write_all(lines.as_bytes())?;
+                writer.flush()?;
+                progress(&row);
+                count += 1;
+            }
+            previous = setpoint as f64;
+        }
+        Ok(count)
+    }
+
+    /// задание уставки и ожидание установления, возвращает установившуюся температуру
+    fn settle(&mut self, setpoint: i32) -> Result<f64, Box<dyn Error>> {
+        self.chamber.set(setpoint)?;
+        let start = Instant::now();
+        let mut readings = 0;
+        loop {
+            let temp = self.chamber.temp()?;
+            if (temp - setpoint as f64).abs() <= self.options.settle_tolerance {
+                readings += 1;
+                if readings >= self.options.settle_readings {
+                    return Ok(temp);
+                }
+            } else {
+                readings = 0;
+            }
+            if start.elapsed() > self.options.settle_timeout {
+                Err(format!(
+                    "Chamber did not settle at {setpoint} in {} s, last reading {temp:.2}",
+                    self.options.settle_timeout.as_secs()
+                ))?
+            }
+            std::thread::sleep(self.options.poll_interval);
+        }
+    }
+}
+
+/// уставки из строки вида `20,-50,70` или диапазона `-50:70:6`
+pub fn parse_setpoints(s: &str, points: usize) -> Result<Vec<(i32, usize)>, Box<dyn Error>> {
+    let invalid = || format!("Invalid setpoints `{s}`, expected T1,T2,... or START:END:STEP");
+    let values: Vec<i32> = match s.split(':').collect::<Vec<_>>()[..] {
+        [start, end, step] => {
+            let (start, end): (i32, i32) = (
+                start.trim().parse().map_err(|_| invalid())?,
+                end.trim().parse().map_err(|_| invalid())?,
+            );
+            let step: usize = step.trim().parse().map_err(|_| invalid())?;
+            if step == 0 {
+                Err(invalid())?
+            }
+            if start <= end {
+                (start..=end).step_by(step).collect()
+            } else {
+                (end..=start).rev().step_by(step).collect()
+            }
+        }
+        [list] => list
+            .split(',')
+            .map(|value| value.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?,
+        _ => Err(invalid())?,
+    };
+    Ok(values.into_iter().map(|temp| (temp, points)).collect())
+}
+
+#[test]
+fn acquisition_with_mock() {
+    use crate::thermo_model::DataFrame;
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || mock::serve(listener, mock::MockDevice::default()).is_ok());
+
+    let timeout = Duration::from_secs(5);
+    let mut acquisition = Acquisition {
+        chamber: Chamber(Link::connect(&address, timeout).unwrap()),
+        sensor: Sensor(Link::connect(&address, timeout).unwrap()),
+        options: AcquisitionOptions {
+            settle_readings: 2,
+            poll_interval: Duration::ZERO,
+            sample_interval: Duration::ZERO,
+            label: "18.10.2026".to_string(),
+            ..Default::default()
+        },
+    };
+    assert!(acquisition.chamber.0.query("BOOM").is_err());
+
+    let setpoints = parse_setpoints("30:70:20", 2).unwrap();
+    assert_eq!(setpoints, vec![(30, 2), (50, 2), (70, 2)]);
+    let mut setpoints = setpoints;
+    setpoints.extend(parse_setpoints("40,-20", 1).unwrap());
+    assert!(parse_setpoints("40;-20", 1).is_err());
+
+    let mut table = b"temp;x;y\r\n".to_vec();
+    let mut messages = 0;
+    let count = acquisition
+        .run(&setpoints, &mut table, |_| messages += 1)
+        .unwrap();
+    assert_eq!(count, 8);
+    assert!(messages > count);
+
+    let table = String::from_utf8(table).unwrap();
+    assert!(table.contains("# plus 18.10.2026;;\r\n30;"));
+    let frame: DataFrame = table.parse().unwrap();
+    let rows: usize = frame
+        .segments
+        .iter()
+        .map(|segment| segment.rows.len())
+        .sum();
+    assert_eq!(rows, 8);
+    assert_eq!(frame.segments.len(), 2);
+    assert_eq!(frame.segments[1].direction(), Direction::Minus);
+    // в имитации положение по x уходит с ростом температуры вниз, по y — вверх
+    let hot = frame.rows.iter().find(|row| row.temp == Some(70)).unwrap();
+    assert!(hot.x.unwrap() < 0 && hot.y.unwrap() > 0);
+}
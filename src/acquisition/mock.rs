@@ -0,0 +1,104 @@
+//! Имитация термокамеры и датчика положения для проверки снятия данных без оборудования
+
+use std::error::Error;
+use std::net::TcpListener;
+
+use crate::link;
+
+/// температура, при которой положение равно `offset`
+const BASE_TEMP: f64 = 20.0;
+
+/// Имитируемое устройство: камера и датчик положения с линейным уходом от температуры
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockDevice {
+    pub temp: f64,
+    pub setpoint: f64,
+    /// доля отставания от уставки, на которую температура приближается к ней за одно показание
+    pub response: f64,
+    /// положение при 20 °C
+    pub offset: (i32, i32),
+    /// уход положения по осям x и y на градус
+    pub drift: (f64, f64),
+    /// размах случайного шума положения
+    pub noise: u32,
+    /// состояние генератора шума
+    seed: u64,
+}
+
+impl Default for MockDevice {
+    fn default() -> Self {
+        Self {
+            temp: BASE_TEMP,
+            setpoint: BASE_TEMP,
+            response: 0.5,
+            offset: (2, -52),
+            drift: (-0.05, 0.04),
+            noise: 0,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+}
+
+impl MockDevice {
+    /// ответ на команду протокола
+    pub fn reply(&mut self, command: &str) -> String {
+        let mut words = command.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("*IDN?"), None) => "r-approx mock device".to_string(),
+            (Some("SET"), Some(value)) => match value.parse() {
+                Ok(setpoint) => {
+                    self.setpoint = setpoint;
+                    "OK".to_string()
+                }
+                Err(_) => format!("ERR invalid setpoint `{value}`"),
+            },
+            (Some("SETP?"), None) => format!("{:.2}", self.setpoint),
+            (Some("TEMP?"), None) => {
+                self.temp += (self.setpoint - self.temp) * self.response;
+                format!("{:.2}", self.temp)
+            }
+            (Some("POS?"), None) => {
+                let delta = self.temp - BASE_TEMP;
+                format!(
+                    "{} {}",
+                    self.offset.0 + (self.drift.0 * delta).round() as i32 + self.noise(),
+                    self.offset.1 + (self.drift.1 * delta).round() as i32 + self.noise()
+                )
+            }
+            _ => format!("ERR unknown command `{command}`"),
+        }
+    }
+
+    /// случайное отклонение в пределах `±noise`
+    fn noise(&mut self) -> i32 {
+        if self.noise == 0 {
+            return 0;
+        }
+        // xorshift, воспроизводимый от запуска к запуску
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed % (2 * self.noise as u64 + 1)) as i32 - self.noise as i32
+    }
+}
+
+/// обслуживание подключений к `listener`, каждое в своём потоке с общим устройством `device`
+pub fn serve(listener: TcpListener, device: MockDevice) -> Result<(), Box<dyn Error>> {
+    link::serve(listener, device, MockDevice::reply)
+}
+
+#[test]
+fn mock_replies() {
+    let mut device = MockDevice::default();
+    assert_eq!(device.reply("POS?"), "2 -52");
+    assert_eq!(device.reply("SET 60"), "OK");
+    assert_eq!(device.reply("TEMP?"), "40.00");
+    assert_eq!(device.reply("POS?"), "1 -51");
+    assert!(device.reply("SET hot").starts_with("ERR"));
+    assert!(device.reply("HELLO").starts_with("ERR"));
+
+    device.noise = 1;
+    let values: Vec<i32> = (0..20).map(|_| device.noise()).collect();
+    assert!(values.iter().all(|value| value.abs() <= 1));
+    assert!(values.iter().any(|value| *value != 0));
+}
@@ -0,0 +1,324 @@
+//! Пакетная обработка папок с данными нескольких изделий
+
+use std::error::Error;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::thermo_model::{Artifact, CalcOptions, DataFrame, NameTemplates, ThermoModel};
+
+/// расширения файлов с сырыми данными
+const DATA_EXTENSIONS: [&str; 2] = ["csv", "txt"];
+
+/// Итог обработки одного изделия
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail,
+    Error,
+}
+
+impl std::fmt::Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Verdict::Pass => write!(f, "pass"),
+            Verdict::Fail => write!(f, "fail"),
+            Verdict::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Строка сводной таблицы пакетной обработки
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceReport {
+    pub serial: String,
+    pub path: PathBuf,
+    pub verdict: Verdict,
+    /// число узлов сетки с данными и общее число узлов
+    pub coverage: (usize, usize),
+    /// наибольшее по модулю отклонение сырых данных от модели по обеим осям
+    pub worst_residual: Option<i32>,
+    /// причина отказа или ошибки
+    pub message: String,
+}
+
+impl DeviceReport {
+    /// оценка модели: все узлы сетки покрыты данными, отклонения в пределах допуска
+    pub fn new(
+        serial: &str,
+        path: &Path,
+        model: &ThermoModel,
+        options: &CalcOptions,
+        tolerance: i32,
+    ) -> Self {
+        let coverage = model.raw_data().coverage(options);
+        let missing: Vec<String> = coverage
+            .iter()
+            .filter(|(_, count)| *count == 0)
+            .map(|(temp, _)| temp.to_string())
+            .collect();
+        let (stats_x, stats_y) = model.residual_stats(tolerance);
+        let worst = stats_x.max_abs.max(stats_y.max_abs);
+
+        let mut reasons = vec![];
+        if !missing.is_empty() {
+            reasons.push(format!("no data at {}", missing.join(", ")));
+        }
+        if worst > tolerance {
+            reasons.push(format!("residual {worst} exceeds tolerance {tolerance}"));
+        }
+
+        Self {
+            serial: serial.to_string(),
+            path: path.to_path_buf(),
+            verdict: if reasons.is_empty() {
+                Verdict::Pass
+            } else {
+                Verdict::Fail
+            },
+            coverage: (coverage.len() - missing.len(), coverage.len()),
+            worst_residual: Some(worst),
+            message: reasons.join("; "),
+        }
+    }
+
+    /// изделие, которое не удалось обработать
+    pub fn error(serial: &str, path: &Path, message: &str) -> Self {
+        Self {
+            serial: serial.to_string(),
+            path: path.to_path_buf(),
+            verdict: Verdict::Error,
+            coverage: (0, 0),
+            worst_residual: None,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for DeviceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{:12} {:6} {:>5}/{:<5} {:>6}  {}",
+            self.serial,
+            self.verdict,
+            self.coverage.0,
+            self.coverage.1,
+            self.worst_residual
+                .map_or("-".to_string(), |worst| worst.to_string()),
+            self.path.display()
+        )?;
+        if !self.message.is_empty() {
+            write!(f, "  ({})", self.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// запись сводной таблицы в формате csv с разделителем `;`
+pub fn write_summary<W: Write>(
+    reports: &[DeviceReport],
+    mut writer: W,
+) -> Result<(), Box<dyn Error>> {
+    writeln!(
+        writer,
+        "serial;file;verdict;coverage;worst_residual;message"
+    )?;
+    for report in reports {
+        writeln!(
+            writer,
+            "{};{};{};{}/{};{};{}",
+            report.serial,
+            report.path.display(),
+            report.verdict,
+            report.coverage.0,
+            report.coverage.1,
+            report
+                .worst_residual
+                .map_or(String::default(), |worst| worst.to_string()),
+            report.message.replace(';', ",")
+        )?;
+    }
+    Ok(())
+}
+
+/// поиск файлов с сырыми данными в папке `root` и её подпапках, файлы моделей,
+/// рассчитанных по найденным данным, и объединённые `update` данные пропускаются
+pub fn find_data_files(root: &Path, names: &NameTemplates) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = walk(root, is_data_file)?;
+
+    let models = files
+        .iter()
+        .map(|path| ThermoModel::default_model_path(path, None, names))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.retain(|path| {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        !models.contains(path) && !names.is_merged(&name)
+    });
+    Ok(files)
+}
+
+/// поиск `auto_model` и `ct` файлов в папке `root` и её подпапках: в папке изделия
+/// с обоими файлами берётся только `auto_model`, чтобы изделие не учитывалось дважды
+pub fn find_model_files(
+    root: &Path,
+    names: &NameTemplates,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let is = |artifact: Artifact, path: &Path| {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        names.matches(artifact, &name)
+    };
+    let mut files = walk(root, |path| {
+        is(Artifact::AutoModel, path) || is(Artifact::Ct, path)
+    })?;
+    let models: Vec<PathBuf> = files
+        .iter()
+        .filter(|path| is(Artifact::AutoModel, path))
+        .filter_map(|path| Some(path.parent()?.to_path_buf()))
+        .collect();
+    files.retain(|path| {
+        is(Artifact::AutoModel, path)
+            || !path
+                .parent()
+                .map_or(false, |dir| models.iter().any(|model| model == dir))
+    });
+    Ok(files)
+}
+
+/// файлы папки `root` и её подпапок, подходящие под условие `filter`
+fn walk<F: Fn(&Path) -> bool>(root: &Path, filter: F) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = vec![];
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if filter(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// файл с подходящим расширением и заголовком таблицы
+fn is_data_file(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if !DATA_EXTENSIONS.contains(&extension.as_str()) {
+        return false;
+    }
+
+    match std::fs::read_to_string(path) {
+        Ok(table) => DataFrame::is_table_header(table.lines().next().unwrap_or_default()),
+        Err(_) => false,
+    }
+}
+
+/// обработка `items` в `jobs` потоков, результаты в исходном порядке
+pub fn run_parallel<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.clamp(1, items.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        match items.get(index) {
+                            Some(item) => results.push((index, f(item))),
+                            None => break results,
+                        }
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[test]
+fn batch_helpers() {
+    let squares = run_parallel(&[1, 2, 3, 4, 5], 3, |x| x * x);
+    assert_eq!(squares, vec![1, 4, 9, 16, 25]);
+    assert!(run_parallel(&[] as &[i32], 4, |x| *x).is_empty());
+
+    let root = std::env::temp_dir().join(format!("r-approx-batch-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    for dir in ["2БЛ123", "2БЛ124/run"] {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    std::fs::copy("test/test_data.csv", root.join("2БЛ123/data.csv")).unwrap();
+    // объединённые `update` данные с заголовком таблицы — не отдельное изделие
+    std::fs::copy("test/test_data.csv", root.join("2БЛ123/data_merged.csv")).unwrap();
+    std::fs::copy("test/old_data.txt", root.join("2БЛ123/data_auto_model.txt")).unwrap();
+    std::fs::copy("test/old_data.txt", root.join("2БЛ124/run/old.txt")).unwrap();
+    std::fs::write(root.join("2БЛ124/notes.txt"), "remarks").unwrap();
+
+    let files = find_data_files(&root, &NameTemplates::default()).unwrap();
+    assert_eq!(
+        files,
+        vec![
+            root.join("2БЛ123/data.csv"),
+            root.join("2БЛ124/run/old.txt")
+        ]
+    );
+
+    // после `batch -s` в папке изделия есть и `auto_model`, и `ct` файл
+    std::fs::copy(
+        "test/old_data.txt",
+        root.join("2БЛ123/tpk-k_2БЛ123_2024-3-5_14-7.ct"),
+    )
+    .unwrap();
+    std::fs::copy(
+        "test/old_data.txt",
+        root.join("2БЛ124/tpk-k_2БЛ124_2024-3-5_14-7.ct"),
+    )
+    .unwrap();
+    assert_eq!(
+        find_model_files(&root, &NameTemplates::default()).unwrap(),
+        vec![
+            root.join("2БЛ123/data_auto_model.txt"),
+            root.join("2БЛ124/tpk-k_2БЛ124_2024-3-5_14-7.ct")
+        ]
+    );
+
+    let path = Path::new("test/test_data.csv");
+    let options = CalcOptions::default();
+    let model = ThermoModel::fit(DataFrame::from_path(path).unwrap(), &options);
+    let report = DeviceReport::new("2БЛ123", path, &model, &options, 100);
+    assert_eq!(report.verdict, Verdict::Pass);
+    assert_eq!(report.coverage, (21, 21));
+
+    let report = DeviceReport::new("2БЛ123", path, &model, &options, -1);
+    assert_eq!(report.verdict, Verdict::Fail);
+
+    let mut summary = vec![];
+    write_summary(
+        &[report, DeviceReport::error("2БЛ124", path, "bad; header")],
+        &mut summary,
+    )
+    .unwrap();
+    let summary = String::from_utf8(summary).unwrap();
+    assert_eq!(summary.lines().count(), 3);
+    assert!(summary.ends_with("2БЛ124;test/test_data.csv;error;0/0;;bad, header\n"));
+    std::fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,238 @@
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::error::Error;
+use std::path::PathBuf;
+
+mod acquire;
+mod args;
+mod batch;
+mod device;
+mod history;
+mod model;
+mod plan;
+mod replay;
+mod review;
+mod synth;
+mod update;
+mod watch;
+
+use args::{csv, output_args, serial_arg, settings, text_arg};
+use model::process;
+
+/// описание командной строки
+pub fn command() -> Command<'static> {
+    Command::new(env!("CARGO_PKG_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about(env!("CARGO_PKG_DESCRIPTION"))
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .arg(
+            Arg::new("config_file")
+                .long("config")
+                .value_name("CONFIG FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .global(true)
+                .help("Config file applied after the system and project ones"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .value_name("PROFILE")
+                .global(true)
+                .help("Config profile for the device variant"),
+        )
+        .arg(
+            Arg::new("set")
+                .long("set")
+                .value_name("KEY=VALUE")
+                .action(ArgAction::Append)
+                .global(true)
+                .help("Override a config value, e.g. window=2"),
+        )
+        .arg(
+            Arg::new("predict")
+                .short('p')
+                .long("predict")
+                .value_name("CSV FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required_unless_present("validate")
+                .help("Same as `predict CSV`"),
+        )
+        .arg(
+            Arg::new("validate")
+                .short('v')
+                .long("validate")
+                .value_name("CSV FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required_unless_present("predict")
+                .conflicts_with("predict")
+                .action(ArgAction::Append)
+                .min_values(1)
+                .max_values(2)
+                .help("Same as `validate CSV [MODEL]`"),
+        )
+        .arg(serial_arg())
+        .arg(text_arg())
+        .args(output_args())
+        .subcommands(model::commands())
+        .subcommand(
+            Command::new("config")
+                .about("Work with configuration files")
+                .subcommand_required(true)
+                .subcommand(Command::new("show").about("Print effective settings")),
+        )
+        .subcommands(batch::commands())
+        .subcommand(update::command())
+        .subcommand(review::command())
+        .subcommand(history::command())
+        .subcommand(plan::command())
+        .subcommands(acquire::commands())
+        .subcommands(device::commands())
+        .subcommand(replay::command())
+        .subcommand(synth::command())
+        .subcommand(watch::command())
+}
+
+/// разбор аргументов и выполнение команды
+pub fn run(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    match args.subcommand() {
+        Some(("predict", args)) => process(args, csv(args)?, true, None),
+        Some(("validate", args)) => process(
+            args,
+            csv(args)?,
+            false,
+            args.get_one::<PathBuf>("model").cloned(),
+        ),
+        Some(("diff", args)) => model::diff(args),
+        Some(("export", args)) => model::export(args),
+        Some(("info", args)) => model::info(args, csv(args)?),
+        Some(("config", args)) => match args.subcommand() {
+            Some(("show", args)) => {
+                print!("{}", settings(args)?);
+                Ok(())
+            }
+            _ => Err("Unknown config command")?,
+        },
+        Some(("fleet", args)) => batch::fleet(args),
+        Some(("batch", args)) => batch::batch(args),
+        Some(("update", args)) => update::update(args),
+        Some(("review", args)) => review::review(args),
+        Some(("history", args)) => match args.subcommand() {
+            Some(("list", args)) => history::history_list(args),
+            Some(("drift", args)) => history::history_drift(args),
+            _ => Err("Unknown history command")?,
+        },
+        Some(("plan", args)) => plan::plan(args),
+        Some(("acquire", args)) => acquire::acquire(args),
+        Some(("mock", args)) => acquire::mock(args),
+        Some(("upload", args)) => device::upload(args),
+        Some(("device-mock", args)) => device::device_mock(args),
+        Some(("emulate", args)) => device::emulate(args),
+        Some(("replay", args)) => replay::replay(args),
+        Some(("synth", args)) => synth::synth(args),
+        Some(("watch", args)) => watch::watch(args),
+        _ => legacy(args),
+    }
+}
+
+/// запуск через ключи `-p`, `-v`, `-s`
+fn legacy(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = args.get_one::<PathBuf>("predict") {
+        return process(args, path.to_owned(), true, None);
+    }
+
+    let values: Vec<&PathBuf> = args
+        .get_many::<PathBuf>("validate")
+        .unwrap_or_default()
+        .collect();
+    let path = values.first().ok_or("Invalid argument")?;
+
+    process(
+        args,
+        path.to_path_buf(),
+        false,
+        values.get(1).map(|path| path.to_path_buf()),
+    )
+}
+
+#[test]
+fn legacy_and_subcommands() {
+    let args = command()
+        .try_get_matches_from(["r-approx", "-v", "a.csv", "b.txt", "-s"])
+        .unwrap();
+    assert!(args.subcommand().is_none());
+    assert_eq!(args.get_many::<PathBuf>("validate").unwrap().count(), 2);
+    assert!(args.contains_id("serial_number"));
+
+    let args = command()
+        .try_get_matches_from([
+            "r-approx",
+            "predict",
+            "a.csv",
+            "-s",
+            "2БЛ1",
+            "--outputs",
+            "md",
+        ])
+        .unwrap();
+    let (_, args) = args.subcommand().unwrap();
+    assert_eq!(
+        args::outputs(args, "outputs", vec![]).unwrap(),
+        [r_approx::Artifact::Md]
+    );
+    let args = command()
+        .try_get_matches_from(["r-approx", "predict", "a.csv", "-s", "2БЛ1"])
+        .unwrap();
+    let (_, args) = args.subcommand().unwrap();
+    assert_eq!(
+        args::outputs(args, "outputs", args::default_outputs(true)).unwrap(),
+        [
+            r_approx::Artifact::Plot,
+            r_approx::Artifact::Md,
+            r_approx::Artifact::AutoModel,
+            r_approx::Artifact::Ct
+        ]
+    );
+
+    let args = command()
+        .try_get_matches_from(["r-approx", "predict", "a.csv", "--no-open"])
+        .unwrap();
+    let (name, args) = args.subcommand().unwrap();
+    assert_eq!(name, "predict");
+    assert!(args.get_flag("no_open"));
+    assert_eq!(args::text_columns(args), None);
+
+    let args = command()
+        .try_get_matches_from(["r-approx", "predict", "--text=80", "a.csv"])
+        .unwrap();
+    assert_eq!(args::text_columns(args.subcommand().unwrap().1), Some(80));
+    assert!(command()
+        .try_get_matches_from(["r-approx", "predict", "--text", "a.csv"])
+        .unwrap()
+        .subcommand()
+        .is_some());
+
+    assert!(command()
+        .try_get_matches_from(["r-approx", "-p", "a.csv", "info", "a.csv"])
+        .is_err());
+    assert!(command().try_get_matches_from(["r-approx"]).is_err());
+
+    let args = command()
+        .try_get_matches_from(["r-approx", "config", "show", "--profile", "narrow"])
+        .unwrap();
+    let (_, args) = args.subcommand().unwrap();
+    let (_, args) = args.subcommand().unwrap();
+    assert_eq!(args.get_one::<String>("profile").unwrap(), "narrow");
+
+    assert!(command()
+        .try_get_matches_from([
+            "r-approx",
+            "update",
+            "--patch",
+            "m.txt",
+            "a.csv",
+            "--old-weight",
+            "20"
+        ])
+        .is_err());
+}
@@ -0,0 +1,195 @@
+//! Снятие сырых данных со стенда и его имитация
+
+use chrono::Local;
+use clap::{Arg, ArgMatches, Command};
+use std::error::Error;
+use std::io::Write;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use r_approx::acquisition::mock::MockDevice;
+use r_approx::acquisition::{self, Acquisition, AcquisitionOptions, Chamber, Link, Sensor};
+use r_approx::Plan;
+
+use super::args::{baud, baud_arg, settings, LINK_TIMEOUT};
+
+/// команды `acquire`, `mock`
+pub(super) fn commands() -> Vec<Command<'static>> {
+    vec![
+        Command::new("acquire")
+            .about("Collect raw data from the thermal chamber and the position sensor over TCP")
+            .arg(
+                Arg::new("output")
+                    .value_name("CSV FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(true)
+                    .help("Raw data file, new segments are appended"),
+            )
+            .arg(
+                Arg::new("chamber")
+                    .long("chamber")
+                    .value_name("ADDRESS")
+                    .required(true)
+                    .help("Chamber controller address, HOST:PORT or a serial port"),
+            )
+            .arg(
+                Arg::new("sensor")
+                    .long("sensor")
+                    .value_name("ADDRESS")
+                    .help("Position sensor address [default: --chamber]"),
+            )
+            .arg(baud_arg())
+            .arg(
+                Arg::new("setpoints")
+                    .long("setpoints")
+                    .value_name("SETPOINTS")
+                    .required_unless_present("plan")
+                    .conflicts_with("plan")
+                    .help("Setpoints in order: 20,-50,70 or -50:70:6"),
+            )
+            .arg(
+                Arg::new("plan")
+                    .long("plan")
+                    .value_name("CSV FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help("Plan file written by `plan` with setpoints and numbers of points"),
+            )
+            .arg(
+                Arg::new("points")
+                    .long("points")
+                    .value_name("POINTS")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("3")
+                    .help("Points at each of --setpoints"),
+            )
+            .arg(
+                Arg::new("settle_tolerance")
+                    .long("settle-tolerance")
+                    .value_name("DEGREES")
+                    .value_parser(clap::value_parser!(f64))
+                    .help("Chamber is settled within this band [default: 0.5]"),
+            )
+            .arg(
+                Arg::new("settle_timeout")
+                    .long("settle-timeout")
+                    .value_name("MINUTES")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("Maximum settling time at a setpoint [default: 120]"),
+            )
+            .arg(
+                Arg::new("poll_interval")
+                    .long("poll-interval")
+                    .value_name("SECONDS")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("Interval between chamber readings while settling [default: 10]"),
+            )
+            .arg(
+                Arg::new("sample_interval")
+                    .long("sample-interval")
+                    .value_name("SECONDS")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("Interval between points at a setpoint [default: `sample_minutes` from settings]"),
+            ),
+        Command::new("mock")
+            .about("Simulate the chamber and the position sensor for `acquire`")
+            .arg(
+                Arg::new("listen")
+                    .long("listen")
+                    .value_name("HOST:PORT")
+                    .default_value("127.0.0.1:5025")
+                    .help("Address to listen on"),
+            )
+            .arg(
+                Arg::new("noise")
+                    .long("noise")
+                    .value_name("COUNTS")
+                    .value_parser(clap::value_parser!(u32))
+                    .default_value("0")
+                    .help("Amplitude of random noise of positions"),
+            ),
+    ]
+}
+
+/// снятие сырых данных по уставкам с дописыванием новых сегментов в файл
+pub(super) fn acquire(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let path = args
+        .get_one::<PathBuf>("output")
+        .ok_or("Invalid argument")?;
+    let settings = settings(args)?;
+    let setpoints = match args.get_one::<PathBuf>("plan") {
+        Some(plan) => Plan::read_setpoints(std::fs::File::open(plan)?)?,
+        None => acquisition::parse_setpoints(
+            args.get_one::<String>("setpoints")
+                .ok_or("Invalid argument")?,
+            *args.get_one::<usize>("points").unwrap_or(&3),
+        )?,
+    };
+    if setpoints.is_empty() {
+        Err("No setpoints to measure")?
+    }
+
+    let seconds = |id: &str| {
+        args.get_one::<u64>(id)
+            .map(|value| Duration::from_secs(*value))
+    };
+    let default = AcquisitionOptions::default();
+    let options = AcquisitionOptions {
+        settle_tolerance: *args
+            .get_one::<f64>("settle_tolerance")
+            .unwrap_or(&default.settle_tolerance),
+        settle_timeout: args
+            .get_one::<u64>("settle_timeout")
+            .map_or(default.settle_timeout, |minutes| {
+                Duration::from_secs(minutes * 60)
+            }),
+        poll_interval: seconds("poll_interval").unwrap_or(default.poll_interval),
+        sample_interval: seconds("sample_interval").unwrap_or(Duration::from_secs(
+            settings.plan_options().sample_minutes as u64 * 60,
+        )),
+        label: Local::now().format("%d.%m.%Y").to_string(),
+        ..default
+    };
+
+    let chamber = args
+        .get_one::<String>("chamber")
+        .ok_or("Invalid argument")?;
+    let sensor = args.get_one::<String>("sensor").unwrap_or(chamber);
+    let timeout = Duration::from_secs(LINK_TIMEOUT);
+    let mut acquisition = Acquisition {
+        chamber: Chamber(Link::open(chamber, baud(args), timeout)?),
+        sensor: Sensor(Link::open(sensor, baud(args), timeout)?),
+        options,
+    };
+
+    // новые сегменты дописываются к файлу с абсолютными координатами
+    let table = std::fs::read_to_string(path).unwrap_or_default();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    if table.trim().is_empty() {
+        file.write_all(b"temp;x;y\r\n")?;
+    } else if table.lines().next().unwrap_or_default().trim_end() != "temp;x;y" {
+        Err(format!(
+            "{} is not a raw data file with header temp;x;y",
+            path.display()
+        ))?
+    } else if !table.ends_with('\n') {
+        file.write_all(b"\r\n")?;
+    }
+
+    let count = acquisition.run(&setpoints, &mut file, |message| println!("{message}"))?;
+    println!("points: {count}, data: {}", path.display());
+    Ok(())
+}
+
+/// имитация камеры и датчика положения до остановки по `Ctrl+C`
+pub(super) fn mock(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let address = args.get_one::<String>("listen").ok_or("Invalid argument")?;
+    let listener = TcpListener::bind(address)?;
+    println!("mock device: {}, Ctrl+C to stop", listener.local_addr()?);
+    let mut device = MockDevice::default();
+    device.noise = *args.get_one::<u32>("noise").unwrap_or(&0);
+    acquisition::mock::serve(listener, device)
+}
@@ -0,0 +1,380 @@
+//! Аргументы командной строки и их разбор, общие для команд
+
+use clap::{Arg, ArgAction, ArgMatches};
+use regex::Regex;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use r_approx::config::{self, Settings};
+use r_approx::history::History;
+use r_approx::link;
+use r_approx::{parse_resolution, Artifact, Format, NameTemplates, Override, PlotOptions, Prior};
+
+const SERIAL_PATTERN: &str = r"[0-9]?БЛ[А-Я]?[0-9]*[А-Я]?";
+/// ширина текстового графика, если ширину терминала узнать нельзя
+const TEXT_COLUMNS: u16 = 100;
+/// время ожидания подключения и ответа устройства, с
+pub(super) const LINK_TIMEOUT: u64 = 10;
+
+pub(super) fn csv_arg() -> Arg<'static> {
+    Arg::new("csv")
+        .value_name("CSV FILE")
+        .value_parser(clap::value_parser!(PathBuf))
+        .required(true)
+}
+
+pub(super) fn serial_arg() -> Arg<'static> {
+    Arg::new("serial_number")
+        .short('s')
+        .long("serial_number")
+        .value_name("SERIAL NUMBER")
+        .required(false)
+        .min_values(0)
+        .max_values(1)
+        .help("Write ct file, the serial number is taken from the folder name if omitted")
+}
+
+pub(super) fn out_dir_arg() -> Arg<'static> {
+    Arg::new("out_dir")
+        .long("out-dir")
+        .value_name("DIR")
+        .value_parser(clap::value_parser!(PathBuf))
+        .help("Folder for output files instead of the folder of the CSV file")
+}
+
+pub(super) fn name_arg() -> Arg<'static> {
+    Arg::new("name")
+        .long("name")
+        .value_name("OUTPUT=TEMPLATE")
+        .action(ArgAction::Append)
+        .help("Output file name template, e.g. md={stem}_model.md or merged={stem}_merged.csv, supports {stem}, {serial}, {date}, {ext}")
+}
+
+/// параметры графика
+pub(super) fn plot_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("plot")
+            .long("plot")
+            .value_name("IMAGE FILE")
+            .value_parser(clap::value_parser!(PathBuf))
+            .help("Path to the plot, format is chosen by the extension"),
+        Arg::new("format")
+            .long("format")
+            .value_name("FORMAT")
+            .value_parser(["png", "svg"])
+            .help("Plot format, overrides the extension of --plot"),
+        Arg::new("resolution")
+            .long("resolution")
+            .value_name("WIDTHxHEIGHT")
+            .help("Plot resolution in pixels [default: 1800x1100]"),
+        Arg::new("dpi")
+            .long("dpi")
+            .value_name("DPI")
+            .value_parser(clap::value_parser!(u32).range(1..))
+            .help("Plot DPI, scales fonts and lines [default: 96]"),
+        Arg::new("tolerance")
+            .long("tolerance")
+            .value_name("TOLERANCE")
+            .value_parser(clap::value_parser!(i32).range(0..))
+            .help("Tolerance band of the residual panel [default: 1]"),
+        switching_arg(),
+        Arg::new("no_open")
+            .long("no-open")
+            .action(ArgAction::SetTrue)
+            .help("Do not open the plot, implied when there is no display"),
+    ]
+}
+
+pub(super) fn text_arg() -> Arg<'static> {
+    Arg::new("text")
+        .long("text")
+        .value_name("COLUMNS")
+        .value_parser(clap::value_parser!(u16).range(20..))
+        .min_values(0)
+        .require_equals(true)
+        .help("Print the plot as text, e.g. over SSH [default: terminal width]")
+}
+
+/// ширина текстового графика из ключа `--text=COLUMNS`, иначе ширина терминала
+pub(super) fn text_columns(args: &ArgMatches) -> Option<usize> {
+    if !args.contains_id("text") {
+        return None;
+    }
+    let columns = match args.get_one::<u16>("text") {
+        Some(columns) => *columns,
+        None => crossterm::terminal::size()
+            .map(|(columns, _)| columns)
+            .ok()
+            .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+            .unwrap_or(TEXT_COLUMNS),
+    };
+    Some(columns as usize)
+}
+
+pub(super) fn switching_arg() -> Arg<'static> {
+    Arg::new("switching")
+        .long("switching")
+        .value_name("RULE")
+        .help("Step switching rule of the device firmware: midpoint, node or hysteresis:DEGREES [default: midpoint]")
+}
+
+pub(super) fn baud_arg() -> Arg<'static> {
+    Arg::new("baud")
+        .long("baud")
+        .value_name("BAUD")
+        .value_parser(clap::value_parser!(u32))
+        .help("Serial port speed [default: 115200]")
+}
+
+pub(super) fn baud(args: &ArgMatches) -> u32 {
+    *args.get_one::<u32>("baud").unwrap_or(&link::DEFAULT_BAUD)
+}
+
+/// параметры априорной модели для расчёта
+pub(super) fn prior_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("prior")
+            .long("prior")
+            .value_name("MODEL FILE")
+            .value_parser(clap::value_parser!(PathBuf))
+            .help("Prior model, e.g. the fleet mean, for nodes with few points"),
+        Arg::new("prior_weight")
+            .long("prior-weight")
+            .value_name("POINTS")
+            .value_parser(clap::value_parser!(u32))
+            .help("Weight of the prior model in number of points [default: 3]"),
+    ]
+}
+
+pub(super) fn history_arg() -> Arg<'static> {
+    Arg::new("history")
+        .long("history")
+        .value_name("DIR")
+        .value_parser(clap::value_parser!(PathBuf))
+        .help("Calibration history folder [default: `history` from settings]")
+}
+
+/// ручные правки узлов модели
+pub(super) fn override_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("override")
+            .long("override")
+            .value_name("TEMP=X,Y")
+            .action(ArgAction::Append)
+            .requires("reason")
+            .help("Force a node value, one of X and Y may be omitted: 52=,-20"),
+        Arg::new("overrides")
+            .long("overrides")
+            .value_name("TOML FILE")
+            .value_parser(clap::value_parser!(PathBuf))
+            .help("File with [[override]] tables: temp, x, y, author, reason"),
+        Arg::new("author")
+            .long("author")
+            .value_name("NAME")
+            .help("Author of --override [default: current user]"),
+        Arg::new("reason")
+            .long("reason")
+            .value_name("TEXT")
+            .help("Reason of --override"),
+    ]
+}
+
+/// параметры выходных файлов, общие для расчёта и валидации
+pub(super) fn output_args() -> Vec<Arg<'static>> {
+    let mut args = plot_args();
+    args.extend([
+        Arg::new("residuals")
+            .long("residuals")
+            .action(ArgAction::SetTrue)
+            .help("Add a panel with deviations of raw data from the model"),
+        Arg::new("outputs")
+            .long("outputs")
+            .value_name("OUTPUTS")
+            .value_parser(["plot", "md", "auto_model", "ct"])
+            .use_value_delimiter(true)
+            .action(ArgAction::Append)
+            .help("Comma separated list of produced files [default: plot,md,auto_model on predict, ct with -s]"),
+        out_dir_arg(),
+        name_arg(),
+        history_arg().help("Record the calibration into the history folder"),
+    ]);
+    args
+}
+
+pub(super) fn csv(args: &ArgMatches) -> Result<PathBuf, Box<dyn Error>> {
+    Ok(args
+        .get_one::<PathBuf>("csv")
+        .ok_or("Invalid argument")?
+        .to_owned())
+}
+
+/// настройки из файлов конфигурации и ключей `--config`, `--profile`, `--set`
+pub(super) fn settings(args: &ArgMatches) -> Result<Settings, Box<dyn Error>> {
+    let files = config::files(
+        &std::env::current_dir()?,
+        args.get_one::<PathBuf>("config_file").map(|x| x.as_path()),
+    )?;
+    let overrides: Vec<String> = args
+        .get_many::<String>("set")
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+
+    Settings::load(
+        &files,
+        args.get_one::<String>("profile").map(|x| x.as_str()),
+        &overrides,
+    )
+}
+
+pub(super) fn names(
+    args: &ArgMatches,
+    settings: &Settings,
+) -> Result<NameTemplates, Box<dyn Error>> {
+    let mut names = settings.names();
+    for template in args.get_many::<String>("name").unwrap_or_default() {
+        names.set_from_str(template)?;
+    }
+    Ok(names)
+}
+
+pub(super) fn plot_options(
+    args: &ArgMatches,
+    settings: &Settings,
+) -> Result<PlotOptions, Box<dyn Error>> {
+    let mut plot_options = settings.plot_options();
+    if let Some(format) = args.get_one::<String>("format") {
+        plot_options.format = Some(format.parse::<Format>()?);
+    }
+    if let Some(resolution) = args.get_one::<String>("resolution") {
+        plot_options.resolution = parse_resolution(resolution)?;
+    }
+    if let Some(dpi) = args.get_one::<u32>("dpi") {
+        plot_options.dpi = *dpi;
+    }
+    plot_options.residuals = matches!(args.try_get_one::<bool>("residuals"), Ok(Some(true)));
+    if let Some(tolerance) = args.get_one::<i32>("tolerance") {
+        plot_options.tolerance = *tolerance;
+    }
+    if let Some(switching) = args.get_one::<String>("switching") {
+        plot_options.switching = switching.parse()?;
+    }
+    Ok(plot_options)
+}
+
+/// априорная модель и её файл из ключей `--prior`, `--prior-weight` или настроек
+pub(super) fn prior(
+    args: &ArgMatches,
+    settings: &Settings,
+) -> Result<Option<(Prior, PathBuf)>, Box<dyn Error>> {
+    let path = match (args.try_get_one::<PathBuf>("prior"), &settings.values.prior) {
+        (Ok(Some(path)), _) => path.to_owned(),
+        (_, Some(path)) => PathBuf::from(path),
+        _ => return Ok(None),
+    };
+    let weight = match args.try_get_one::<u32>("prior_weight") {
+        Ok(Some(weight)) => *weight,
+        _ => settings
+            .values
+            .prior_weight
+            .unwrap_or(config::DEFAULT_PRIOR_WEIGHT),
+    };
+    Ok(Some((Prior::from_path(&path, weight)?, path)))
+}
+
+pub(super) fn outputs(
+    args: &ArgMatches,
+    id: &str,
+    default: Vec<Artifact>,
+) -> Result<Vec<Artifact>, Box<dyn Error>> {
+    // явный список выходных файлов не дополняется `ct` файлом из ключа `-s`
+    if let Some(values) = args.get_many::<String>(id) {
+        return values.map(|x| x.parse()).collect();
+    }
+    let mut outputs = default;
+    if args.contains_id("serial_number") && !outputs.contains(&Artifact::Ct) {
+        outputs.push(Artifact::Ct);
+    }
+    Ok(outputs)
+}
+
+/// серийный номер из ключа `-s` или из имени папки `folder`
+pub(super) fn serial_number(args: &ArgMatches, folder: &Path) -> Result<String, Box<dyn Error>> {
+    match args.get_one::<String>("serial_number") {
+        Some(serial) => Ok(serial.to_owned()),
+        None => detect_serial_number(folder),
+    }
+}
+
+/// поиск серийного номера в пути `folder`: в ближайшем имени с номером,
+/// чтобы папка партии выше по пути не перекрывала номер изделия
+pub(super) fn detect_serial_number(folder: &Path) -> Result<String, Box<dyn Error>> {
+    let re = Regex::new(SERIAL_PATTERN)?;
+
+    folder
+        .iter()
+        .rev()
+        .find_map(|name| Some(re.find(&name.to_string_lossy())?.as_str().to_string()))
+        .ok_or_else(|| {
+            format!(
+                "Can not detect serial pattern {SERIAL_PATTERN} in {}",
+                folder.display()
+            )
+            .into()
+        })
+}
+
+/// история калибровок из ключа `--history` или настроек
+pub(super) fn history(args: &ArgMatches, settings: &Settings) -> Option<History> {
+    match args.try_get_one::<PathBuf>("history") {
+        Ok(Some(dir)) => Some(History::new(dir)),
+        _ => settings.history(),
+    }
+}
+
+/// ручные правки из файла `--overrides` и ключей `--override`
+pub(super) fn overrides(args: &ArgMatches) -> Result<Vec<Override>, Box<dyn Error>> {
+    let mut overrides = match args.try_get_one::<PathBuf>("overrides") {
+        Ok(Some(path)) => Override::from_path(path)?,
+        _ => vec![],
+    };
+    if let Ok(Some(values)) = args.try_get_many::<String>("override") {
+        let author = author(args)?;
+        let reason = args.get_one::<String>("reason").ok_or("Invalid argument")?;
+        for value in values {
+            overrides.push(Override::parse(value, &author, reason)?);
+        }
+    }
+    Ok(overrides)
+}
+
+/// автор правок из ключа `--author` или имя текущего пользователя
+pub(super) fn author(args: &ArgMatches) -> Result<String, Box<dyn Error>> {
+    match args.get_one::<String>("author") {
+        Some(author) => Ok(author.to_owned()),
+        None => Ok(std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .map_err(|_| "Author is unknown, use --author")?),
+    }
+}
+
+/// выходные файлы, если они не заданы ключом `--outputs`
+pub(super) fn default_outputs(recalc: bool) -> Vec<Artifact> {
+    let mut default = vec![Artifact::Plot, Artifact::Md];
+    if recalc {
+        default.push(Artifact::AutoModel);
+    }
+    default
+}
+
+#[test]
+fn serial_from_path() {
+    assert_eq!(
+        detect_serial_number(Path::new("/data/2БЛ123/run")).unwrap(),
+        "2БЛ123"
+    );
+    assert_eq!(
+        detect_serial_number(Path::new("/data/2БЛ5/tpk-k_2БЛ1_2024-3-5_14-7.ct")).unwrap(),
+        "2БЛ1"
+    );
+}
@@ -0,0 +1,238 @@
+//! Пакетная обработка изделий и статистика партии
+
+use chrono::Local;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use r_approx::batch::{self, DeviceReport, Verdict};
+use r_approx::{display_available, DataFrame, Fleet};
+
+use super::args::{
+    default_outputs, detect_serial_number, history, name_arg, names, output_args, outputs,
+    plot_args, plot_options, prior_args, serial_arg, settings,
+};
+use super::model::{prepare, record};
+
+/// команды `fleet`, `batch`
+pub(super) fn commands() -> Vec<Command<'static>> {
+    vec![
+        Command::new("fleet")
+            .about("Statistics of models of many devices")
+            .arg(
+                Arg::new("paths")
+                    .value_name("PATH")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(true)
+                    .multiple_values(true)
+                    .help("Model files or folders searched for auto_model and ct files"),
+            )
+            .arg(
+                Arg::new("percentile")
+                    .long("percentile")
+                    .value_name("PERCENT")
+                    .value_parser(clap::value_parser!(f64))
+                    .default_value("5")
+                    .help("Lower percentile of the envelope, the upper one is symmetric"),
+            )
+            .arg(
+                Arg::new("md")
+                    .long("md")
+                    .value_name("MD FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help("Write node statistics and outliers as markdown"),
+            )
+            .arg(
+                Arg::new("mean")
+                    .long("mean")
+                    .value_name("MODEL FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help("Write the fleet mean model, usable as --prior"),
+            )
+            .arg(name_arg())
+            .args(plot_args()),
+        Command::new("batch")
+            .about("Process data of many devices, continuing after failures")
+            .arg(
+                Arg::new("paths")
+                    .value_name("PATH")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(true)
+                    .multiple_values(true)
+                    .help("Data files or folders searched for data files"),
+            )
+            .arg(
+                Arg::new("validate")
+                    .long("validate")
+                    .action(ArgAction::SetTrue)
+                    .help("Validate against existing models instead of predicting"),
+            )
+            .arg(
+                Arg::new("jobs")
+                    .short('j')
+                    .long("jobs")
+                    .value_name("JOBS")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("Number of devices processed in parallel [default: number of CPUs]"),
+            )
+            .arg(
+                Arg::new("summary")
+                    .long("summary")
+                    .value_name("CSV FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help("Summary table [default: batch_summary.csv in the output folder]"),
+            )
+            .arg(
+                serial_arg()
+                    .help("Write ct files, serial numbers are taken from the device folders"),
+            )
+            .args(prior_args())
+            .args(output_args()),
+    ]
+}
+
+/// статистика моделей партии изделий
+pub(super) fn fleet(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let settings = settings(args)?;
+    let names = names(args, &settings)?;
+    let plot_options = plot_options(args, &settings)?;
+
+    let mut files = vec![];
+    for path in args.get_many::<PathBuf>("paths").unwrap_or_default() {
+        if path.is_dir() {
+            files.extend(batch::find_model_files(path, &names)?);
+        } else {
+            files.push(path.to_owned());
+        }
+    }
+
+    let mut devices: Vec<(String, DataFrame)> = vec![];
+    for path in files {
+        let mut name = detect_serial_number(&path).or_else(|_| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .ok_or("Invalid file name")
+        })?;
+        if devices.iter().any(|(other, _)| *other == name) {
+            name = path.display().to_string();
+        }
+        devices.push((name, DataFrame::from_path(&path)?));
+    }
+
+    let fleet = Fleet::new(devices, *args.get_one::<f64>("percentile").unwrap_or(&5.0))?;
+    let outliers = fleet.outliers(plot_options.tolerance as f64);
+
+    let mut md = fleet.to_string();
+    md += &format!("\r\ndevices: {}\r\n", fleet.devices.len());
+    for outlier in outliers.iter() {
+        let nodes: Vec<String> = outlier
+            .nodes
+            .iter()
+            .map(|(temp, axis)| format!("{temp}{axis}"))
+            .collect();
+        md += &format!("outlier {}: {}\r\n", outlier.name, nodes.join(", "));
+    }
+    print!("{md}");
+    if let Some(path) = args.get_one::<PathBuf>("md") {
+        std::fs::write(path, md.as_bytes())?;
+    }
+    if let Some(path) = args.get_one::<PathBuf>("mean") {
+        fleet.mean_model().save_file(path)?;
+    }
+
+    let path = args.get_one::<PathBuf>("plot").cloned().unwrap_or_else(|| {
+        PathBuf::from(format!("fleet.{}", plot_options.format_for(Path::new(""))))
+    });
+    fleet.plot(&path, &plot_options, plot_options.tolerance as f64)?;
+    println!("plot: {}", path.display());
+    if !args.get_flag("no_open") && display_available() {
+        opener::open(&path)?;
+    }
+    Ok(())
+}
+
+/// обработка данных нескольких изделий, серийный номер берётся из папки с данными
+pub(super) fn batch(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    if args.get_one::<String>("serial_number").is_some() {
+        Err("Serial numbers are taken from the device folders in batch mode")?
+    }
+
+    let settings = settings(args)?;
+    let names = names(args, &settings)?;
+    let recalc = !args.get_flag("validate");
+    let outputs = outputs(args, "outputs", default_outputs(recalc))?;
+    let calc_options = settings.calc_options()?;
+    let tolerance = plot_options(args, &settings)?.tolerance;
+    let out_dir = args.get_one::<PathBuf>("out_dir");
+    let date = Local::now();
+    let history = history(args, &settings);
+
+    let mut files = vec![];
+    for path in args.get_many::<PathBuf>("paths").unwrap_or_default() {
+        if path.is_dir() {
+            files.extend(batch::find_data_files(path, &names)?);
+        } else {
+            files.push(path.to_owned());
+        }
+    }
+    if files.is_empty() {
+        Err("No data files found")?
+    }
+
+    let jobs = match args.get_one::<usize>("jobs") {
+        Some(jobs) => *jobs,
+        None => std::thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+    };
+    let device = |path: &Path, serial: &str| -> Result<DeviceReport, Box<dyn Error>> {
+        let device_dir = out_dir.map(|dir| dir.join(serial));
+        let (mut model, inputs) =
+            prepare(args, &settings, path, recalc, None, device_dir.as_deref())?;
+        model.with_date(date);
+        model.with_no_open(true);
+        model.with_serial_number(serial);
+        model.save(&outputs)?;
+
+        let report = DeviceReport::new(serial, path, &model, &calc_options, tolerance);
+        if let Some(history) = &history {
+            let command = if recalc { "predict" } else { "validate" };
+            record(history, &report, command, date, &model, &inputs, &outputs)?;
+        }
+        Ok(report)
+    };
+    let reports = batch::run_parallel(&files, jobs, |path| {
+        let report = match detect_serial_number(path.parent().unwrap_or(path)) {
+            // ошибка одного изделия не прерывает пакет
+            Ok(serial) => device(path, &serial)
+                .unwrap_or_else(|err| DeviceReport::error(&serial, path, &err.to_string())),
+            Err(err) => DeviceReport::error("?", path, &err.to_string()),
+        };
+        eprintln!("{}: {}", path.display(), report.verdict);
+        report
+    });
+
+    println!(
+        "{:12} {:6} {:>11} {:>6}  file",
+        "serial", "result", "coverage", "worst"
+    );
+    for report in reports.iter() {
+        println!("{report}");
+    }
+
+    let summary = match args.get_one::<PathBuf>("summary") {
+        Some(path) => path.to_owned(),
+        None => out_dir
+            .map_or(PathBuf::from("."), |dir| dir.to_owned())
+            .join("batch_summary.csv"),
+    };
+    batch::write_summary(&reports, std::fs::File::create(&summary)?)?;
+    println!("summary: {}", summary.display());
+
+    let failed = reports
+        .iter()
+        .filter(|report| report.verdict != Verdict::Pass)
+        .count();
+    if failed > 0 {
+        Err(format!("{failed} of {} device(s) failed", reports.len()))?
+    }
+    Ok(())
+}
@@ -0,0 +1,200 @@
+//! Загрузка таблицы в изделие, имитация изделия и эмуляция коррекции
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::error::Error;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use r_approx::acquisition::Link;
+use r_approx::device::{self, Device};
+use r_approx::{read_profile, DataFrame, Firmware};
+
+use super::args::{
+    baud, baud_arg, detect_serial_number, serial_arg, settings, switching_arg, LINK_TIMEOUT,
+};
+
+/// команды `upload`, `device-mock`, `emulate`
+pub(super) fn commands() -> Vec<Command<'static>> {
+    vec![
+        Command::new("upload")
+            .about("Upload the correction table to the device and verify it by reading back")
+            .arg(
+                Arg::new("model")
+                    .value_name("MODEL FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(true)
+                    .help("Table to upload: ct or auto_model file"),
+            )
+            .arg(
+                Arg::new("port")
+                    .long("port")
+                    .value_name("ADDRESS")
+                    .required(true)
+                    .help("Device address, HOST:PORT or a serial port"),
+            )
+            .arg(baud_arg())
+            .arg(
+                Arg::new("verify")
+                    .long("verify")
+                    .action(ArgAction::SetTrue)
+                    .help("Only read the table back and compare it with the model"),
+            )
+            .arg(
+                serial_arg().help(
+                    "Expected serial number of the device [default: from the model file name]",
+                ),
+            ),
+        Command::new("device-mock")
+            .about("Simulate a device for `upload`")
+            .arg(
+                Arg::new("listen")
+                    .long("listen")
+                    .value_name("HOST:PORT")
+                    .default_value("127.0.0.1:5026")
+                    .help("Address to listen on"),
+            )
+            .arg(
+                Arg::new("pty")
+                    .long("pty")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("listen")
+                    .help("Simulate a serial port on a pseudo terminal instead of TCP"),
+            )
+            .arg(
+                Arg::new("serial")
+                    .long("serial")
+                    .value_name("SERIAL NUMBER")
+                    .default_value("2БЛ1")
+                    .help("Serial number of the simulated device"),
+            )
+            .arg(
+                Arg::new("corrupt")
+                    .long("corrupt")
+                    .value_name("TEMP")
+                    .value_parser(clap::value_parser!(i32))
+                    .help("Store this node with an error to test verification"),
+            ),
+        Command::new("emulate")
+            .about("Replay a temperature profile through the device table lookup")
+            .arg(
+                Arg::new("model")
+                    .value_name("MODEL FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(true)
+                    .help("Table stored in the device: ct or auto_model file"),
+            )
+            .arg(
+                Arg::new("temp_profile")
+                    .value_name("PROFILE FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(true)
+                    .help("Temperature profile time;temp, time in minutes"),
+            )
+            .arg(switching_arg())
+            .arg(
+                Arg::new("output")
+                    .long("output")
+                    .value_name("CSV FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help("Applied correction time;temp;dx;dy [default: standard output]"),
+            ),
+    ]
+}
+
+/// загрузка таблицы в изделие, чтение и сверка с моделью
+pub(super) fn upload(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let path = args.get_one::<PathBuf>("model").ok_or("Invalid argument")?;
+    let table = DataFrame::from_path(path)?;
+    let address = args.get_one::<String>("port").ok_or("Invalid argument")?;
+    let mut device = Device(Link::open(
+        address,
+        baud(args),
+        Duration::from_secs(LINK_TIMEOUT),
+    )?);
+
+    // без явного номера номер ищется в имени файла модели, например tpk-k_2БЛ1_...ct
+    let expected = match args.get_one::<String>("serial_number") {
+        Some(serial) => Some(serial.to_owned()),
+        None => detect_serial_number(path).ok(),
+    };
+    let serial = device.serial_number()?;
+    println!("device: {serial}");
+    if let Some(expected) = expected {
+        if expected != serial {
+            Err(format!(
+                "Device {serial} does not match the model of {expected}"
+            ))?
+        }
+    }
+
+    if !args.get_flag("verify") {
+        device.upload(&table)?;
+        println!("uploaded: {} nodes", table.rows.len());
+    }
+    let mismatches = device::compare(&table, &device.read_table()?);
+    if !mismatches.is_empty() {
+        for mismatch in mismatches.iter() {
+            println!("{mismatch}");
+        }
+        Err(format!(
+            "Verification failed: {} nodes differ",
+            mismatches.len()
+        ))?
+    }
+    println!("verified: {}", path.display());
+    Ok(())
+}
+
+/// имитация изделия по TCP или на псевдотерминале до остановки по `Ctrl+C`
+pub(super) fn device_mock(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let mut device =
+        device::mock::MockDevice::new(args.get_one::<String>("serial").ok_or("Invalid argument")?);
+    device.corrupt = args.get_one::<i32>("corrupt").copied();
+
+    if args.get_flag("pty") {
+        #[cfg(unix)]
+        return device::mock::serve_pty(device, |path| {
+            println!("mock device: {path}, Ctrl+C to stop")
+        });
+        #[cfg(not(unix))]
+        Err("Pseudo terminals are not supported on this system")?
+    }
+    let address = args.get_one::<String>("listen").ok_or("Invalid argument")?;
+    let listener = TcpListener::bind(address)?;
+    println!("mock device: {}, Ctrl+C to stop", listener.local_addr()?);
+    device::mock::serve(listener, device)
+}
+
+/// коррекция, которую изделие применяет по ходу температурного профиля
+pub(super) fn emulate(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let path = args.get_one::<PathBuf>("model").ok_or("Invalid argument")?;
+    let profile = args
+        .get_one::<PathBuf>("temp_profile")
+        .ok_or("Invalid argument")?;
+    let switching = match args.get_one::<String>("switching") {
+        Some(switching) => switching.parse()?,
+        None => settings(args)?.plot_options().switching,
+    };
+
+    let profile = read_profile(std::fs::File::open(profile)?)?;
+    let temps: Vec<f64> = profile.iter().map(|(_, temp)| *temp).collect();
+    let applied = Firmware::new(&DataFrame::from_path(path)?, switching)?.replay(&temps);
+
+    let mut table = "time;temp;dx;dy\n".to_string();
+    for ((time, temp), (x, y)) in profile.iter().zip(applied.iter()) {
+        table += &format!("{time};{temp};{x};{y}\n");
+    }
+    match args.get_one::<PathBuf>("output") {
+        Some(output) => {
+            std::fs::write(output, table)?;
+            let switches = applied.windows(2).filter(|pair| pair[0] != pair[1]).count();
+            println!(
+                "switching: {switching}, switches: {switches}, output: {}",
+                output.display()
+            );
+        }
+        None => print!("{table}"),
+    }
+    Ok(())
+}
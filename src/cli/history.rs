@@ -0,0 +1,87 @@
+//! Просмотр истории калибровок
+
+use clap::{Arg, ArgMatches, Command};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use r_approx::display_available;
+use r_approx::history::{self, History};
+
+use super::args::{history, history_arg, plot_args, plot_options, settings};
+
+/// команда `history`
+pub(super) fn command() -> Command<'static> {
+    Command::new("history")
+        .about("Query the calibration history")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("list")
+                .about("List calibrations of a device or of all devices")
+                .arg(
+                    Arg::new("serial")
+                        .value_name("SERIAL NUMBER")
+                        .help("Device serial number [default: all devices]"),
+                )
+                .arg(history_arg()),
+        )
+        .subcommand(
+            Command::new("drift")
+                .about("Show how the model of a device changed between calibrations")
+                .arg(
+                    Arg::new("serial")
+                        .value_name("SERIAL NUMBER")
+                        .required(true)
+                        .help("Device serial number"),
+                )
+                .arg(history_arg())
+                .args(plot_args()),
+        )
+}
+
+/// список калибровок из истории
+pub(super) fn history_list(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let history = required_history(args)?;
+    let entries = history.entries(args.get_one::<String>("serial").map(|s| s.as_str()))?;
+    for entry in entries.iter() {
+        println!("{}", entry.record);
+    }
+    println!("calibrations: {}", entries.len());
+    Ok(())
+}
+
+/// изменения модели изделия между калибровками и их график
+pub(super) fn history_drift(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let history = required_history(args)?;
+    let settings = settings(args)?;
+    let plot_options = plot_options(args, &settings)?;
+    let serial = args
+        .get_one::<String>("serial")
+        .ok_or("Serial number is required")?;
+
+    let entries = history.entries(Some(serial))?;
+    if entries.is_empty() {
+        Err(format!("No calibrations of {serial} in the history"))?
+    }
+    for (label, diff) in history::drift(&entries)? {
+        println!("{label}: max change {}", diff.max_change());
+    }
+
+    let path = args.get_one::<PathBuf>("plot").cloned().unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "{serial}_drift.{}",
+            plot_options.format_for(Path::new(""))
+        ))
+    });
+    history::drift_plot(&entries, &path, &plot_options)?;
+    println!("plot: {}", path.display());
+    if !args.get_flag("no_open") && display_available() {
+        opener::open(&path)?;
+    }
+    Ok(())
+}
+
+fn required_history(args: &ArgMatches) -> Result<History, Box<dyn Error>> {
+    let settings = settings(args)?;
+    Ok(history(args, &settings)
+        .ok_or("History folder is not set, use --history or `history` in config")?)
+}
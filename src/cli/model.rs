@@ -0,0 +1,320 @@
+//! Расчёт, валидация, сравнение и экспорт модели одного изделия
+
+use chrono::{DateTime, Local};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use r_approx::batch::DeviceReport;
+use r_approx::config::Settings;
+use r_approx::history::{History, Record};
+use r_approx::{display_available, Artifact, DataFrame, ModelDiff, ThermoModel};
+
+use super::args::{
+    csv_arg, default_outputs, detect_serial_number, history, name_arg, names, out_dir_arg,
+    output_args, outputs, override_args, overrides, plot_args, plot_options, prior, prior_args,
+    serial_arg, serial_number, settings, text_arg, text_columns,
+};
+
+/// команды `predict`, `validate`, `diff`, `export`, `info`
+pub(super) fn commands() -> Vec<Command<'static>> {
+    vec![
+        Command::new("predict")
+            .about("Calculate the model from raw data")
+            .arg(csv_arg())
+            .arg(serial_arg())
+            .args(prior_args())
+            .args(override_args())
+            .arg(text_arg())
+            .args(output_args()),
+        Command::new("validate")
+            .about("Check raw data against a calculated model")
+            .arg(csv_arg())
+            .arg(
+                Arg::new("model")
+                    .value_name("MODEL FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help("Model file [default: auto_model next to the CSV file]"),
+            )
+            .arg(serial_arg())
+            .arg(text_arg())
+            .args(output_args()),
+        Command::new("diff")
+            .about("Compare two model tables node by node")
+            .arg(
+                Arg::new("old")
+                    .value_name("OLD MODEL")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(true),
+            )
+            .arg(
+                Arg::new("new")
+                    .value_name("NEW MODEL")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(true),
+            )
+            .arg(
+                Arg::new("threshold")
+                    .long("threshold")
+                    .value_name("THRESHOLD")
+                    .value_parser(clap::value_parser!(i32).range(0..))
+                    .help("Maximum allowed change of a node, exit with an error if exceeded"),
+            )
+            .arg(
+                Arg::new("md")
+                    .long("md")
+                    .value_name("MD FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help("Write the comparison as a markdown section"),
+            )
+            .args(plot_args()),
+        Command::new("export")
+            .about("Write a calculated model as ct or md file")
+            .arg(
+                Arg::new("model")
+                    .value_name("MODEL FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(true),
+            )
+            .arg(
+                Arg::new("to")
+                    .long("to")
+                    .value_name("OUTPUTS")
+                    .value_parser(["md", "auto_model", "ct"])
+                    .use_value_delimiter(true)
+                    .action(ArgAction::Append)
+                    .default_value("ct"),
+            )
+            .arg(serial_arg())
+            .arg(out_dir_arg())
+            .arg(name_arg()),
+        Command::new("info")
+            .about("Show segments and coverage of a data or model file")
+            .arg(csv_arg()),
+    ]
+}
+
+/// расчёт или валидация модели по файлу `path`
+pub(super) fn process(
+    args: &ArgMatches,
+    path: PathBuf,
+    recalc: bool,
+    optional_path: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let settings = settings(args)?;
+    let outputs = outputs(args, "outputs", default_outputs(recalc))?;
+    let date = Local::now();
+
+    let (mut model, inputs) = prepare(
+        args,
+        &settings,
+        &path,
+        recalc,
+        optional_path,
+        args.get_one::<PathBuf>("out_dir").map(|dir| dir.as_path()),
+    )?;
+    model.with_date(date);
+    model.with_ct_dir(std::env::current_dir()?);
+    model.with_no_open(args.get_flag("no_open"));
+    if outputs.contains(&Artifact::Ct) {
+        model.with_serial_number(&serial_number(args, &std::env::current_dir()?)?);
+    }
+    model.save(&outputs)?;
+    for item in model.adjustments() {
+        println!("constraint {item}");
+    }
+    if let Some(columns) = text_columns(args) {
+        model.write_text(std::io::stdout().lock(), columns)?;
+    }
+
+    if let Some(history) = history(args, &settings) {
+        let serial = serial_number(args, &std::env::current_dir()?)
+            .or_else(|_| detect_serial_number(path.parent().unwrap_or(&path)))?;
+        let report = DeviceReport::new(
+            &serial,
+            &path,
+            &model,
+            &settings.calc_options()?,
+            plot_options(args, &settings)?.tolerance,
+        );
+        let command = if recalc { "predict" } else { "validate" };
+        let dir = record(&history, &report, command, date, &model, &inputs, &outputs)?;
+        println!("history: {}", dir.display());
+    }
+    Ok(())
+}
+
+/// запись калибровки в историю
+pub(super) fn record(
+    history: &History,
+    report: &DeviceReport,
+    command: &str,
+    date: DateTime<Local>,
+    model: &ThermoModel,
+    inputs: &[PathBuf],
+    outputs: &[Artifact],
+) -> Result<PathBuf, Box<dyn Error>> {
+    let outputs = outputs
+        .iter()
+        .map(|artifact| model.artifact_path(*artifact))
+        .collect::<Result<Vec<_>, _>>()?;
+    history.add(
+        &Record::new(report, command, date, inputs, &outputs)?,
+        model.calc_data(),
+    )
+}
+
+/// модель по файлу `path` с параметрами из настроек и командной строки
+/// и использованные входные файлы, выходные файлы пишутся в папку `out_dir`
+pub(super) fn prepare(
+    args: &ArgMatches,
+    settings: &Settings,
+    path: &Path,
+    recalc: bool,
+    mut optional_path: Option<PathBuf>,
+    out_dir: Option<&Path>,
+) -> Result<(ThermoModel, Vec<PathBuf>), Box<dyn Error>> {
+    if !recalc && optional_path.is_none() {
+        let names = names(args, settings)?;
+        optional_path = Some(ThermoModel::default_model_path(path, out_dir, &names)?);
+    }
+    let mut inputs = vec![path.to_path_buf()];
+
+    let calc_options = settings.calc_options()?;
+    let mut model = match prior(args, settings)? {
+        Some((prior, prior_path)) if recalc => {
+            inputs.push(prior_path);
+            let mut model =
+                ThermoModel::fit_with_prior(DataFrame::from_path(path)?, &calc_options, &prior);
+            model.with_source_path(path);
+            model
+        }
+        _ => ThermoModel::from_path(path, recalc, optional_path.as_deref(), &calc_options)?,
+    };
+    if recalc {
+        model.with_overrides(&overrides(args)?)?;
+        if let Ok(Some(path)) = args.try_get_one::<PathBuf>("overrides") {
+            inputs.push(path.to_owned());
+        }
+    } else {
+        inputs.extend(optional_path);
+    }
+    configure(&mut model, args, settings, out_dir)?;
+    Ok((model, inputs))
+}
+
+/// имена, папка и параметры графика выходных файлов модели
+pub(super) fn configure(
+    model: &mut ThermoModel,
+    args: &ArgMatches,
+    settings: &Settings,
+    out_dir: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    model.with_names(names(args, settings)?);
+    if let Some(dir) = out_dir {
+        model.with_out_dir(dir)?;
+    }
+    model.with_plot_options(plot_options(args, settings)?);
+    if let Some(path) = args.get_one::<PathBuf>("plot") {
+        model.with_plot_path(path);
+    }
+    Ok(())
+}
+
+/// сравнение двух моделей: таблица, раздел markdown и график
+pub(super) fn diff(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let old_path = args.get_one::<PathBuf>("old").ok_or("Invalid argument")?;
+    let new_path = args.get_one::<PathBuf>("new").ok_or("Invalid argument")?;
+    let threshold = args.get_one::<i32>("threshold").copied();
+    let plot_options = plot_options(args, &settings(args)?)?;
+
+    let diff = ModelDiff::new(
+        &DataFrame::from_path(old_path)?,
+        &DataFrame::from_path(new_path)?,
+        threshold,
+    );
+    println!("{diff}");
+
+    let file_name = |path: &Path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let labels = (file_name(old_path), file_name(new_path));
+    if let Some(path) = args.get_one::<PathBuf>("md") {
+        std::fs::write(path, diff.md((&labels.0, &labels.1)).as_bytes())?;
+    }
+
+    let plot_path = match args.get_one::<PathBuf>("plot") {
+        Some(path) => path.to_owned(),
+        None => {
+            let mut name = new_path
+                .file_stem()
+                .ok_or("Invalid file name")?
+                .to_os_string();
+            name.push(format!("_diff.{}", plot_options.format_for(Path::new(""))));
+            new_path.with_file_name(name)
+        }
+    };
+    diff.plot(&plot_path, &plot_options, (&labels.0, &labels.1))?;
+    println!("plot: {}", plot_path.display());
+    if !args.get_flag("no_open") && display_available() {
+        opener::open(&plot_path)?;
+    }
+
+    if let Some(threshold) = threshold.filter(|_| diff.exceeds()) {
+        Err(format!(
+            "Change {} exceeds threshold {threshold}",
+            diff.max_change()
+        ))?
+    }
+    Ok(())
+}
+
+pub(super) fn export(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let path = args.get_one::<PathBuf>("model").ok_or("Invalid argument")?;
+    let outputs = outputs(args, "to", vec![])?;
+
+    let mut model = ThermoModel::from_model_path(path)?;
+    model.with_names(names(args, &settings(args)?)?);
+    model.with_date(Local::now());
+    model.with_ct_dir(std::env::current_dir()?);
+    if let Some(dir) = args.get_one::<PathBuf>("out_dir") {
+        model.with_out_dir(dir)?;
+    }
+    if outputs.contains(&Artifact::Ct) {
+        model.with_serial_number(&serial_number(args, &std::env::current_dir()?)?);
+    }
+
+    model.save(&outputs)
+}
+
+pub(super) fn info(args: &ArgMatches, path: PathBuf) -> Result<(), Box<dyn Error>> {
+    let options = settings(args)?.calc_options()?;
+    let frame = DataFrame::from_path(&path)?;
+
+    println!("file: {}", path.display());
+    println!("rows: {}", frame.rows.len());
+    if let (Some(first), Some(last)) = (frame.rows.first(), frame.rows.last()) {
+        println!(
+            "temp: {}..{}",
+            first.temp.unwrap_or_default(),
+            last.temp.unwrap_or_default()
+        );
+    }
+
+    for segment in frame.segments.iter() {
+        println!(
+            "segment `{}` ({:?}): {} rows",
+            segment.comment,
+            segment.direction(),
+            segment.rows.len()
+        );
+    }
+
+    println!("coverage:");
+    for (temp, count) in frame.coverage(&options) {
+        println!("  {temp:4}: {count}");
+    }
+    Ok(())
+}
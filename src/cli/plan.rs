@@ -0,0 +1,47 @@
+//! План дополнительных измерений
+
+use clap::{Arg, ArgMatches, Command};
+use std::error::Error;
+use std::path::PathBuf;
+
+use r_approx::{DataFrame, Plan};
+
+use super::args::{csv, csv_arg, settings};
+
+/// команда `plan`
+pub(super) fn command() -> Command<'static> {
+    Command::new("plan")
+        .about("List chamber setpoints that still need measurements")
+        .arg(csv_arg())
+        .arg(
+            Arg::new("plan")
+                .long("plan")
+                .value_name("CSV FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Plan file for the chamber controller [default: DATA_plan.csv]"),
+        )
+}
+
+/// план дополнительных измерений по покрытию и разбросу данных в узлах сетки
+pub(super) fn plan(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let path = csv(args)?;
+    let settings = settings(args)?;
+    let plan = Plan::new(
+        &DataFrame::from_path(&path)?,
+        &settings.calc_options()?,
+        &settings.plan_options(),
+    );
+    print!("{plan}");
+
+    let plan_path = match args.get_one::<PathBuf>("plan") {
+        Some(plan_path) => plan_path.to_owned(),
+        None => {
+            let mut name = path.file_stem().unwrap_or_default().to_os_string();
+            name.push("_plan.csv");
+            path.with_file_name(name)
+        }
+    };
+    plan.write_csv(std::fs::File::create(&plan_path)?)?;
+    println!("plan: {}", plan_path.display());
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! Ошибка наведения на температурном профиле
+
+use clap::{Arg, ArgMatches, Command};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use r_approx::{display_available, read_profile, DataFrame, Replay};
+
+use super::args::{csv, csv_arg, plot_args, plot_options, settings};
+
+/// команда `replay`
+pub(super) fn command() -> Command<'static> {
+    Command::new("replay")
+        .about("Residual pointing error over a temperature profile with the table applied")
+        .arg(csv_arg().help("Raw data used to estimate the drift"))
+        .arg(
+            Arg::new("temp_profile")
+                .value_name("PROFILE FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required(true)
+                .help("Temperature profile time;temp, time in minutes"),
+        )
+        .arg(
+            Arg::new("model")
+                .long("model")
+                .value_name("MODEL FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Table stored in the device: ct or auto_model file [default: calculated from the raw data]"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("CSV FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Error over time time;temp;dx;dy;error_x;error_y"),
+        )
+        .args(plot_args())
+}
+
+/// остаточная ошибка наведения на температурном профиле: статистика, таблица и график
+pub(super) fn replay(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let path = csv(args)?;
+    let profile = args
+        .get_one::<PathBuf>("temp_profile")
+        .ok_or("Invalid argument")?;
+    let settings = settings(args)?;
+    let calc_options = settings.calc_options()?;
+    let plot_options = plot_options(args, &settings)?;
+
+    let raw = DataFrame::from_path(&path)?;
+    let table = match args.get_one::<PathBuf>("model") {
+        Some(model) => DataFrame::from_path(model)?,
+        None => raw.calc(&calc_options),
+    };
+    let replay = Replay::new(
+        &raw,
+        &table,
+        &read_profile(std::fs::File::open(profile)?)?,
+        &calc_options,
+        plot_options.switching,
+    )?;
+
+    let (x, y, both) = replay.stats(plot_options.tolerance);
+    println!(
+        "switching: {}, tolerance: ±{}",
+        replay.switching, plot_options.tolerance
+    );
+    println!("  x: {x}");
+    println!("  y: {y}");
+    println!("both axes in tolerance: {both:.1}% of time");
+    println!(
+        "outside measured range {}..{}: {:.1}% of time",
+        replay.measured.0,
+        replay.measured.1,
+        replay.outside()
+    );
+    if let Some(output) = args.get_one::<PathBuf>("output") {
+        replay.write_csv(std::fs::File::create(output)?)?;
+        println!("output: {}", output.display());
+    }
+
+    let plot_path = match args.get_one::<PathBuf>("plot") {
+        Some(path) => path.to_owned(),
+        None => {
+            let mut name = path.file_stem().ok_or("Invalid file name")?.to_os_string();
+            name.push(format!(
+                "_replay.{}",
+                plot_options.format_for(Path::new(""))
+            ));
+            path.with_file_name(name)
+        }
+    };
+    replay.plot(&plot_path, &plot_options)?;
+    println!("plot: {}", plot_path.display());
+    if !args.get_flag("no_open") && display_available() {
+        opener::open(&plot_path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! Интерактивный просмотр и правка модели
+
+use chrono::Local;
+use clap::{Arg, ArgMatches, Command};
+use std::error::Error;
+use std::path::PathBuf;
+
+use crate::tui::{self, Review};
+use r_approx::batch::DeviceReport;
+use r_approx::{Artifact, ThermoModel};
+
+use super::args::{
+    author, csv, csv_arg, detect_serial_number, history, output_args, outputs, plot_options,
+    prior_args, serial_arg, serial_number, settings,
+};
+use super::model::{configure, prepare, record};
+
+/// команда `review`
+pub(super) fn command() -> Command<'static> {
+    Command::new("review")
+        .about("Review and edit the model in the terminal")
+        .arg(csv_arg())
+        .arg(
+            Arg::new("model")
+                .value_name("MODEL FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Model file to start from [default: calculate from the CSV file]"),
+        )
+        .arg(serial_arg())
+        .arg(
+            Arg::new("author")
+                .long("author")
+                .value_name("NAME")
+                .help("Author of the changes [default: current user]"),
+        )
+        .args(prior_args())
+        .args(output_args())
+}
+
+/// интерактивный просмотр и правка модели с сохранением в auto_model или `ct` файл
+pub(super) fn review(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let settings = settings(args)?;
+    let path = csv(args)?;
+    let model_path = args.get_one::<PathBuf>("model").cloned();
+    let recalc = model_path.is_none();
+    let outputs = outputs(args, "outputs", vec![Artifact::AutoModel, Artifact::Md])?;
+    let out_dir = args.get_one::<PathBuf>("out_dir").map(|dir| dir.as_path());
+    let calc_options = settings.calc_options()?;
+    let author = author(args)?;
+
+    let (model, inputs) = prepare(args, &settings, &path, recalc, model_path, out_dir)?;
+    let title = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let review = Review::new(
+        &title,
+        model.raw_data().clone(),
+        model.calc_data().clone(),
+        calc_options,
+    )?;
+
+    tui::run(review, |review, note| {
+        if note.trim().is_empty() {
+            Err("Audit note is required")?
+        }
+        let date = Local::now();
+        let mut model = ThermoModel::new(review.kept(), review.calc_data().clone());
+        model.with_source_path(&path);
+        configure(&mut model, args, &settings, out_dir)?;
+        model.with_overrides(&review.overrides(&author, note))?;
+        model.with_note(&review.note(&author, note));
+        model.with_date(date);
+        model.with_ct_dir(std::env::current_dir()?);
+        model.with_no_open(true);
+        if outputs.contains(&Artifact::Ct) {
+            model.with_serial_number(&serial_number(args, &std::env::current_dir()?)?);
+        }
+        model.save(&outputs)?;
+
+        let paths = outputs
+            .iter()
+            .map(|artifact| Ok(model.artifact_path(*artifact)?.display().to_string()))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        if let Some(history) = history(args, &settings) {
+            let serial = serial_number(args, &std::env::current_dir()?)
+                .or_else(|_| detect_serial_number(path.parent().unwrap_or(&path)))?;
+            let report = DeviceReport::new(
+                &serial,
+                &path,
+                &model,
+                &calc_options,
+                plot_options(args, &settings)?.tolerance,
+            );
+            record(&history, &report, "review", date, &model, &inputs, &outputs)?;
+        }
+        Ok(format!("saved: {}", paths.join(", ")))
+    })
+}
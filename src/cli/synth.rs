@@ -0,0 +1,182 @@
+//! Синтетические сырые данные и проверка восстановления кривой
+
+use clap::{Arg, ArgMatches, Command};
+use std::error::Error;
+use std::path::PathBuf;
+
+use r_approx::synth::{self, SynthOptions, TrueCurve};
+use r_approx::{CalcOptions, DataFrame, DataRow, Estimator, ResidualStats};
+
+use super::args::settings;
+
+/// команда `synth`
+pub(super) fn command() -> Command<'static> {
+    Command::new("synth")
+        .about("Generate raw data from a known drift curve to test the calculation")
+        .arg(
+            Arg::new("output")
+                .value_name("CSV FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required(true)
+                .help("Raw data file to write"),
+        )
+        .arg(
+            Arg::new("curve")
+                .long("curve")
+                .value_name("MODEL FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("True drift curve: model or table temp;dx;dy [default: built-in curve]"),
+        )
+        .arg(
+            Arg::new("segments")
+                .long("segments")
+                .value_name("START:END,...")
+                .help(
+                    "Measurement segments, minus when END is below START [default: 20:70,20:-50]",
+                ),
+        )
+        .arg(
+            Arg::new("step")
+                .long("step")
+                .value_name("DEGREES")
+                .value_parser(clap::value_parser!(usize))
+                .help("Temperature step between points [default: 2]"),
+        )
+        .arg(
+            Arg::new("points")
+                .long("points")
+                .value_name("POINTS")
+                .value_parser(clap::value_parser!(usize))
+                .help("Points at each temperature [default: 1]"),
+        )
+        .arg(
+            Arg::new("noise")
+                .long("noise")
+                .value_name("SIGMA")
+                .value_parser(clap::value_parser!(f64))
+                .help("Standard deviation of position noise [default: 0.5]"),
+        )
+        .arg(
+            Arg::new("quantum")
+                .long("quantum")
+                .value_name("COUNTS")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .help("Resolution of the position sensor [default: 1]"),
+        )
+        .arg(
+            Arg::new("hysteresis")
+                .long("hysteresis")
+                .value_name("DEGREES")
+                .value_parser(clap::value_parser!(f64))
+                .help("Thermal lag of positions behind the chamber [default: 1]"),
+        )
+        .arg(
+            Arg::new("baseline")
+                .long("baseline")
+                .value_name("COUNTS")
+                .value_parser(clap::value_parser!(u32))
+                .help("Largest sensor zero offset of a segment [default: 20]"),
+        )
+        .arg(
+            Arg::new("missing")
+                .long("missing")
+                .value_name("START:END,...")
+                .help("Temperature ranges without points"),
+        )
+        .arg(
+            Arg::new("outliers")
+                .long("outliers")
+                .value_name("RATE")
+                .value_parser(clap::value_parser!(f64))
+                .help("Share of outlier points [default: 0.02]"),
+        )
+        .arg(
+            Arg::new("outlier_size")
+                .long("outlier-size")
+                .value_name("COUNTS")
+                .value_parser(clap::value_parser!(i32))
+                .help("Size of outliers [default: 8]"),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("SEED")
+                .value_parser(clap::value_parser!(u64))
+                .help("Seed of the random generator, same seed gives the same file [default: 1]"),
+        )
+}
+
+/// синтетические сырые данные и точность восстановления кривой расчётом
+pub(super) fn synth(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let path = args
+        .get_one::<PathBuf>("output")
+        .ok_or("Invalid argument")?;
+    let curve = match args.get_one::<PathBuf>("curve") {
+        Some(curve) => TrueCurve::from_frame(&DataFrame::from_path(curve)?)?,
+        None => TrueCurve::default(),
+    };
+    let default = SynthOptions::default();
+    let options = SynthOptions {
+        segments: match args.get_one::<String>("segments") {
+            Some(segments) => synth::parse_ranges(segments)?,
+            None => default.segments.clone(),
+        },
+        step: *args.get_one::<usize>("step").unwrap_or(&default.step),
+        points: *args.get_one::<usize>("points").unwrap_or(&default.points),
+        noise: *args.get_one::<f64>("noise").unwrap_or(&default.noise),
+        quantum: *args.get_one::<u32>("quantum").unwrap_or(&default.quantum),
+        hysteresis: *args
+            .get_one::<f64>("hysteresis")
+            .unwrap_or(&default.hysteresis),
+        baseline: *args.get_one::<u32>("baseline").unwrap_or(&default.baseline),
+        missing: match args.get_one::<String>("missing") {
+            Some(missing) => synth::parse_ranges(missing)?,
+            None => vec![],
+        },
+        outlier_rate: *args
+            .get_one::<f64>("outliers")
+            .unwrap_or(&default.outlier_rate),
+        outlier_size: *args
+            .get_one::<i32>("outlier_size")
+            .unwrap_or(&default.outlier_size),
+        seed: *args.get_one::<u64>("seed").unwrap_or(&default.seed),
+        ..default
+    };
+    if options.segments.is_empty() {
+        Err("No segments to generate")?
+    }
+
+    std::fs::write(path, synth::generate(&curve, &options)?)?;
+    let raw = DataFrame::from_path(path)?;
+    println!(
+        "rows: {}, segments: {}, data: {}",
+        raw.rows.len(),
+        raw.segments.len(),
+        path.display()
+    );
+
+    // ошибки модели в узлах относительно истинной кривой по каждому способу оценки
+    let settings = settings(args)?;
+    let calc_options = settings.calc_options()?;
+    let tolerance = settings.plot_options().tolerance;
+    for estimator in [Estimator::Median, Estimator::Mean] {
+        let calc = raw.calc(&CalcOptions {
+            estimator,
+            ..calc_options
+        });
+        let errors = curve.errors(&calc, &raw, calc_options.window);
+        let values = |value: fn(&DataRow) -> Option<i32>| -> Vec<i32> {
+            errors.rows.iter().filter_map(value).collect()
+        };
+        println!("{estimator}:");
+        println!(
+            "  x: {}",
+            ResidualStats::new(&values(|row| row.x), tolerance)
+        );
+        println!(
+            "  y: {}",
+            ResidualStats::new(&values(|row| row.y), tolerance)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,180 @@
+//! Дополнение данных изделия новыми сегментами
+
+use chrono::Local;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use r_approx::batch::DeviceReport;
+use r_approx::{Artifact, DataFrame, ModelDiff, ThermoModel, Update};
+
+use super::args::{
+    default_outputs, detect_serial_number, history, names, output_args, outputs, override_args,
+    overrides, plot_options, serial_arg, serial_number, settings, text_arg, text_columns,
+};
+use super::model::{configure, record};
+
+/// команда `update`
+pub(super) fn command() -> Command<'static> {
+    Command::new("update")
+        .about("Merge new raw segments into device data or update model nodes they cover")
+        .arg(
+            Arg::new("base")
+                .value_name("DATA")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required(true)
+                .help("Existing raw data, or the existing model with --patch"),
+        )
+        .arg(
+            Arg::new("new")
+                .value_name("NEW DATA")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required(true)
+                .multiple_values(true)
+                .help("Raw data of the new measurements"),
+        )
+        .arg(
+            Arg::new("patch")
+                .long("patch")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(&["merged", "old_weight"])
+                .help("Recalculate only the model nodes covered by new data"),
+        )
+        .arg(
+            Arg::new("merged")
+                .long("merged")
+                .value_name("CSV FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Merged raw data file [default: DATA_merged.csv]"),
+        )
+        .arg(
+            Arg::new("old_weight")
+                .long("old-weight")
+                .value_name("PERCENT")
+                .value_parser(clap::value_parser!(u32).range(0..=100))
+                .help("Weight of points of old segments relative to new ones [default: 50]"),
+        )
+        .arg(serial_arg())
+        .args(override_args())
+        .arg(text_arg())
+        .args(output_args())
+}
+
+/// дополнение данных изделия новыми сегментами или пересчёт узлов модели
+/// только в диапазоне температур новых данных
+pub(super) fn update(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let settings = settings(args)?;
+    let calc_options = settings.calc_options()?;
+    let outputs = outputs(args, "outputs", default_outputs(true))?;
+    let out_dir = args.get_one::<PathBuf>("out_dir");
+    let base = args.get_one::<PathBuf>("base").ok_or("Invalid argument")?;
+    let date = Local::now();
+
+    let mut inputs = vec![base.to_owned()];
+    let mut new: Option<DataFrame> = None;
+    for path in args.get_many::<PathBuf>("new").unwrap_or_default() {
+        let frame = segments(path)?;
+        new = Some(match new {
+            Some(new) => Update::new(new, frame, 1.0)?.merged(),
+            None => frame,
+        });
+        inputs.push(path.to_owned());
+    }
+    let new = new.ok_or("No new data files")?;
+
+    let (before, mut model, source) = if args.get_flag("patch") {
+        let before = DataFrame::from_path(base)?;
+        let (calc_data, adjustments) = Update::patch(&before, &new, &calc_options);
+        let mut model = ThermoModel::new(new, calc_data);
+        model.with_adjustments(adjustments);
+        (before, model, inputs[1].to_owned())
+    } else {
+        let weight = match args.get_one::<u32>("old_weight") {
+            Some(weight) => *weight as f64 / 100.0,
+            None => settings.old_weight(),
+        };
+        let update = Update::new(segments(base)?, new, weight)?;
+        let (low, high) = update.range();
+        println!("new data: {low}..{high}");
+
+        let merged = match args.get_one::<PathBuf>("merged") {
+            Some(path) => path.to_owned(),
+            None => ThermoModel::default_merged_path(
+                base,
+                out_dir.map(PathBuf::as_path),
+                &names(args, &settings)?,
+            )?,
+        };
+        if let Some(dir) = merged.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        update.write_merged(std::fs::File::create(&merged)?)?;
+        println!("merged: {}", merged.display());
+
+        let (calc_data, adjustments) = update.calc(&calc_options);
+        let mut model = ThermoModel::new(update.merged(), calc_data);
+        model.with_adjustments(adjustments);
+        // прежняя модель с теми же ограничениями, чтобы в изменениях были только новые данные
+        (update.old.calc(&calc_options), model, merged)
+    };
+
+    model.with_source_path(&source);
+    model.with_overrides(&overrides(args)?)?;
+    inputs.extend(args.get_one::<PathBuf>("overrides").cloned());
+    configure(
+        &mut model,
+        args,
+        &settings,
+        out_dir.map(|dir| dir.as_path()),
+    )?;
+    model.with_date(date);
+    model.with_ct_dir(std::env::current_dir()?);
+    model.with_no_open(args.get_flag("no_open"));
+    if outputs.contains(&Artifact::Ct) {
+        model.with_serial_number(&serial_number(args, &std::env::current_dir()?)?);
+    }
+    model.save(&outputs)?;
+    for item in model.adjustments() {
+        println!("constraint {item}");
+    }
+    if let Some(columns) = text_columns(args) {
+        model.write_text(std::io::stdout().lock(), columns)?;
+    }
+
+    let mut diff = ModelDiff::new(&before, model.calc_data(), Some(0));
+    diff.nodes.retain(|node| node.max_change() > 0);
+    println!("changed nodes: {}", diff.nodes.len());
+    if !diff.nodes.is_empty() {
+        println!("{diff}");
+    }
+
+    if let Some(history) = history(args, &settings) {
+        let serial = serial_number(args, &std::env::current_dir()?)
+            .or_else(|_| detect_serial_number(base.parent().unwrap_or(base)))?;
+        let report = DeviceReport::new(
+            &serial,
+            &source,
+            &model,
+            &calc_options,
+            plot_options(args, &settings)?.tolerance,
+        );
+        let dir = record(&history, &report, "update", date, &model, &inputs, &outputs)?;
+        println!("history: {}", dir.display());
+    }
+    Ok(())
+}
+
+/// сырые данные, сегменты без комментария подписываются именем файла
+fn segments(path: &Path) -> Result<DataFrame, Box<dyn Error>> {
+    let mut frame = DataFrame::from_path(path)?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    for segment in frame.segments.iter_mut() {
+        if segment.comment.is_empty() {
+            segment.comment = name.clone();
+        }
+    }
+    Ok(frame)
+}
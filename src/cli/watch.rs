@@ -0,0 +1,198 @@
+//! Пересчёт модели при изменении сырых данных во время измерения
+
+use chrono::Local;
+use clap::{Arg, ArgMatches, Command};
+use crossterm::{cursor, terminal, tty::IsTty};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use r_approx::batch::DeviceReport;
+use r_approx::config::Settings;
+use r_approx::watch::Watcher;
+use r_approx::Artifact;
+
+use super::args::{
+    default_outputs, names, output_args, outputs, plot_options, prior_args, serial_arg,
+    serial_number, settings, text_arg, text_columns,
+};
+use super::model::prepare;
+
+/// наибольшая длина полоски покрытия узла в режиме `watch`
+const WATCH_BAR: usize = 20;
+
+/// команда `watch`
+pub(super) fn command() -> Command<'static> {
+    Command::new("watch")
+        .about("Recalculate the model whenever the raw data change")
+        .arg(
+            Arg::new("path")
+                .value_name("CSV FILE OR DIR")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required(true)
+                .help("Raw data file or folder with raw data files"),
+        )
+        .arg(
+            Arg::new("interval")
+                .long("interval")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("2")
+                .help("Interval between checks of the files"),
+        )
+        .arg(serial_arg())
+        .args(prior_args())
+        .arg(text_arg())
+        .args(
+            output_args()
+                .into_iter()
+                .filter(|arg| arg.get_id() != "history"),
+        )
+}
+
+/// пересчёт модели и отчёт о покрытии сетки при каждом изменении сырых данных
+pub(super) fn watch(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let path = args.get_one::<PathBuf>("path").ok_or("Invalid argument")?;
+    let interval = Duration::from_secs(*args.get_one::<u64>("interval").unwrap_or(&2));
+    let settings = settings(args)?;
+    let mut watcher = Watcher::new(path, names(args, &settings)?);
+
+    println!("watching {}, Ctrl+C to stop", path.display());
+    loop {
+        let changed = watcher.changed()?;
+        if !changed.is_empty() && std::io::stdout().is_tty() {
+            crossterm::execute!(
+                std::io::stdout(),
+                terminal::Clear(terminal::ClearType::All),
+                cursor::MoveTo(0, 0)
+            )?;
+        }
+        for file in changed {
+            // файл может быть дописан не до конца, ошибка выводится до следующего изменения
+            if let Err(err) = watch_report(args, &settings, &file) {
+                println!("{}: {err}", file.display());
+            }
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+/// расчёт модели по файлу `path`, сохранение выходных файлов и отчёт о покрытии сетки
+fn watch_report(args: &ArgMatches, settings: &Settings, path: &Path) -> Result<(), Box<dyn Error>> {
+    let outputs = outputs(args, "outputs", default_outputs(true))?;
+    let date = Local::now();
+    let (mut model, _) = prepare(
+        args,
+        settings,
+        path,
+        true,
+        None,
+        args.get_one::<PathBuf>("out_dir").map(|dir| dir.as_path()),
+    )?;
+    model.with_date(date);
+    model.with_ct_dir(std::env::current_dir()?);
+    model.with_no_open(true);
+    if outputs.contains(&Artifact::Ct) {
+        model.with_serial_number(&serial_number(args, &std::env::current_dir()?)?);
+    }
+    model.save(&outputs)?;
+
+    let options = settings.calc_options()?;
+    let min_points = settings.plan_options().min_points;
+    let raw = model.raw_data();
+    println!(
+        "{} {}: {} rows, {} segments",
+        date.format("%H:%M:%S"),
+        path.display(),
+        raw.rows.len(),
+        raw.segments.len()
+    );
+    let serial = serial_number(args, path.parent().unwrap_or(path)).unwrap_or_default();
+    let report = DeviceReport::new(
+        &serial,
+        path,
+        &model,
+        &options,
+        plot_options(args, settings)?.tolerance,
+    );
+    println!("{report}");
+
+    let coverage = raw.coverage(&options);
+    let covered = coverage
+        .iter()
+        .filter(|(_, count)| *count >= min_points)
+        .count();
+    println!(
+        "coverage: {covered}/{} nodes with at least {min_points} points",
+        coverage.len()
+    );
+    for (temp, count) in coverage {
+        let mark = if count < min_points {
+            "  need data"
+        } else {
+            ""
+        };
+        println!(
+            "  {temp:4}: {count:3} {}{mark}",
+            "#".repeat(count.min(WATCH_BAR))
+        );
+    }
+
+    let gaps: Vec<String> = raw
+        .gaps(&options, min_points)
+        .iter()
+        .map(|(first, last)| {
+            if first == last {
+                first.to_string()
+            } else {
+                format!("{first}..{last}")
+            }
+        })
+        .collect();
+    if gaps.is_empty() {
+        println!("grid covered");
+    } else {
+        println!("need data at: {}", gaps.join(", "));
+    }
+
+    if let Some(columns) = text_columns(args) {
+        model.write_text(std::io::stdout().lock(), columns)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn watch_partial_file() {
+    let dir = std::env::temp_dir().join(format!(
+        "r-approx-watch-partial-test-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("data.csv");
+    let args = super::command()
+        .try_get_matches_from([
+            "r-approx",
+            "watch",
+            &path.to_string_lossy(),
+            "--outputs",
+            "md",
+        ])
+        .unwrap();
+    let (_, args) = args.subcommand().unwrap();
+    let settings = settings(args).unwrap();
+
+    // стенд начинает сегмент строкой комментария, точек в нём ещё нет
+    std::fs::write(&path, "temp;x;y\r\n# plus 01.01.2024;;\r\n").unwrap();
+    assert!(watch_report(args, &settings, &path).is_err());
+    // строка дописана не до конца
+    std::fs::write(&path, "temp;x;y\r\n# plus 01.01.2024;;\r\n20;5").unwrap();
+    assert!(watch_report(args, &settings, &path).is_err());
+    std::fs::write(&path, "temp;x;y\r\n# plus 01.01.2024;;\r\n20;5;\r\n").unwrap();
+    assert!(watch_report(args, &settings, &path).is_err());
+    assert!(!dir.join("data_model.md").exists());
+    std::fs::write(&path, "temp;x;y\r\n# plus 01.01.2024;;\r\n20;5;5\r\n").unwrap();
+    watch_report(args, &settings, &path).unwrap();
+    assert!(dir.join("data_model.md").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
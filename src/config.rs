@@ -0,0 +1,541 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::history::History;
+use crate::thermo_model::{
+    CalcOptions, Constraints, NameTemplates, PlanOptions, PlotOptions, Prior, Switching,
+};
+
+/// файл настроек проекта в текущей папке
+pub const PROJECT_CONFIG: &str = "r-approx.toml";
+pub const DEFAULT_PROFILE: &str = "default";
+/// вес априорной модели в числе точек по умолчанию
+pub const DEFAULT_PRIOR_WEIGHT: u32 = 3;
+/// вес прежних сегментов при дополнении данных по умолчанию, %
+pub const DEFAULT_OLD_WEIGHT: u32 = 50;
+
+/// Профиль настроек, незаданные значения берутся из профиля `default`,
+/// а затем из встроенных значений по умолчанию
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub grid_start: Option<i32>,
+    pub grid_end: Option<i32>,
+    pub grid_step: Option<usize>,
+    pub window: Option<i32>,
+    pub estimator: Option<String>,
+    /// допустимый диапазон значений таблицы по осям, по умолчанию не ограничен
+    pub min_x: Option<i32>,
+    pub max_x: Option<i32>,
+    pub min_y: Option<i32>,
+    pub max_y: Option<i32>,
+    /// наибольшая разность значений соседних узлов, по умолчанию не ограничена
+    pub max_step: Option<u32>,
+    /// монотонная по температуре таблица
+    pub monotonic: Option<bool>,
+    pub tolerance: Option<i32>,
+    /// правило переключения ступеней в изделии: `midpoint`, `node` или `hysteresis:2`
+    pub switching: Option<String>,
+    pub x_axis_name: Option<String>,
+    pub y_axis_name: Option<String>,
+    pub font: Option<String>,
+    pub plot_width: Option<u32>,
+    pub plot_height: Option<u32>,
+    pub dpi: Option<u32>,
+    pub ct_name: Option<String>,
+    /// априорная модель для узлов с малым числом точек, например средняя по партии
+    pub prior: Option<String>,
+    pub prior_weight: Option<u32>,
+    /// папка истории калибровок
+    pub history: Option<String>,
+    /// вес точек прежних сегментов при дополнении данных, %
+    pub old_weight: Option<u32>,
+    /// наименьшее число точек в окне узла, при котором узел считается покрытым данными
+    pub min_points: Option<u32>,
+    /// наибольший разброс точек в окне узла, при большем узел измеряется повторно
+    pub max_spread: Option<i32>,
+    /// время установления температуры в камере, мин
+    pub settle_minutes: Option<u32>,
+    /// время снятия одной точки, мин
+    pub sample_minutes: Option<u32>,
+    /// скорость изменения температуры камеры, °C/ч
+    pub ramp_rate: Option<u32>,
+}
+
+impl Profile {
+    /// встроенные значения по умолчанию
+    fn builtin() -> Self {
+        let calc = CalcOptions::default();
+        let plot = PlotOptions::default();
+        let names = NameTemplates::default();
+        let plan = PlanOptions::default();
+
+        Self {
+            grid_start: Some(calc.grid_start),
+            grid_end: Some(calc.grid_end),
+            grid_step: Some(calc.grid_step),
+            window: Some(calc.window),
+            estimator: Some(calc.estimator.to_string()),
+            min_x: None,
+            max_x: None,
+            min_y: None,
+            max_y: None,
+            max_step: None,
+            monotonic: Some(calc.constraints.monotonic),
+            tolerance: Some(plot.tolerance),
+            switching: Some(plot.switching.to_string()),
+            x_axis_name: Some(plot.axis_names.0),
+            y_axis_name: Some(plot.axis_names.1),
+            font: Some(plot.font),
+            plot_width: Some(plot.resolution.0),
+            plot_height: Some(plot.resolution.1),
+            dpi: Some(plot.dpi),
+            ct_name: Some(names.ct),
+            prior: None,
+            prior_weight: Some(DEFAULT_PRIOR_WEIGHT),
+            history: None,
+            old_weight: Some(DEFAULT_OLD_WEIGHT),
+            min_points: Some(plan.min_points as u32),
+            max_spread: Some(plan.max_spread),
+            settle_minutes: Some(plan.settle_minutes),
+            sample_minutes: Some(plan.sample_minutes),
+            ramp_rate: Some(plan.ramp_rate),
+        }
+    }
+
+    /// относительные пути к файлам и папкам отсчитываются от папки `dir` файла настроек
+    fn resolve_paths(&mut self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        for path in [&mut self.prior, &mut self.history].into_iter().flatten() {
+            if Path::new(path).is_relative() {
+                *path = dir
+                    .join(&path)
+                    .to_str()
+                    .ok_or(format!("Invalid path `{}`", dir.display()))?
+                    .to_string();
+            }
+        }
+        Ok(())
+    }
+
+    /// наложение заданных значений профиля `other`
+    fn merge(&mut self, other: &Profile) {
+        macro_rules! merge {
+            ($($field:ident),*) => {
+                $(if other.$field.is_some() {
+                    self.$field = other.$field.clone();
+                })*
+            };
+        }
+        merge!(
+            grid_start,
+            grid_end,
+            grid_step,
+            window,
+            estimator,
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+            max_step,
+            monotonic,
+            tolerance,
+            switching,
+            x_axis_name,
+            y_axis_name,
+            font,
+            plot_width,
+            plot_height,
+            dpi,
+            ct_name,
+            prior,
+            prior_weight,
+            history,
+            old_weight,
+            min_points,
+            max_spread,
+            settle_minutes,
+            sample_minutes,
+            ramp_rate
+        );
+    }
+}
+
+/// Содержимое файла настроек
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// профиль, выбранный по умолчанию
+    pub profile: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl ConfigFile {
+    pub fn from_path(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut item: Self = toml::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|err| format!("Invalid config {}: {err}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for profile in item.profiles.values_mut() {
+            profile.resolve_paths(dir)?;
+        }
+        Ok(item)
+    }
+
+    /// наложение файла с большим приоритетом
+    fn merge(&mut self, other: ConfigFile) {
+        if other.profile.is_some() {
+            self.profile = other.profile;
+        }
+        for (name, profile) in other.profiles {
+            self.profiles.entry(name).or_default().merge(&profile);
+        }
+    }
+}
+
+/// Итоговые настройки с учётом всех источников
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub profile: String,
+    pub values: Profile,
+    /// прочитанные файлы в порядке возрастания приоритета
+    pub sources: Vec<PathBuf>,
+}
+
+impl std::fmt::Display for Settings {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "# profile: {}", self.profile)?;
+        for source in self.sources.iter() {
+            writeln!(f, "# source: {}", source.display())?;
+        }
+        write!(
+            f,
+            "{}",
+            toml::to_string(&self.values).map_err(|_| std::fmt::Error)?
+        )
+    }
+}
+
+impl Settings {
+    /// настройки из файлов `files` (по возрастанию приоритета), профиля `profile`
+    /// и переопределений вида `ключ=значение`
+    pub fn load(
+        files: &[PathBuf],
+        profile: Option<&str>,
+        overrides: &[String],
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut config = ConfigFile::default();
+        let mut sources = vec![];
+        for path in files {
+            config.merge(ConfigFile::from_path(path)?);
+            sources.push(path.to_owned());
+        }
+
+        let profile = profile
+            .map(|name| name.to_string())
+            .or(config.profile.clone())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+        if profile != DEFAULT_PROFILE && !config.profiles.contains_key(&profile) {
+            Err(format!("Unknown profile `{profile}`"))?
+        }
+
+        let mut values = Profile::builtin();
+        if let Some(default) = config.profiles.get(DEFAULT_PROFILE) {
+            values.merge(default);
+        }
+        if let Some(selected) = config.profiles.get(&profile) {
+            values.merge(selected);
+        }
+        for item in overrides {
+            values.merge(&parse_override(item)?);
+        }
+
+        let item = Self {
+            profile,
+            values,
+            sources,
+        };
+        item.check()?;
+        Ok(item)
+    }
+
+    /// проверка итоговых значений, чтобы ошибка в файле настроек не проявлялась
+    /// позже пустой сеткой или пустым графиком
+    fn check(&self) -> Result<(), Box<dyn Error>> {
+        let options = self.calc_options()?;
+        if options.window < 0 {
+            Err(format!(
+                "Invalid window {}, expected 0 or more",
+                options.window
+            ))?
+        }
+        if let Some(switching) = &self.values.switching {
+            switching.parse::<Switching>()?;
+        }
+        let plot = self.plot_options();
+        if plot.resolution.0 == 0 || plot.resolution.1 == 0 || plot.dpi == 0 {
+            Err(format!(
+                "Invalid plot size {}x{} at {} dpi",
+                plot.resolution.0, plot.resolution.1, plot.dpi
+            ))?
+        }
+        if let Some(weight) = self.values.old_weight.filter(|weight| *weight > 100) {
+            Err(format!("Invalid old_weight {weight}, expected 0..100 %"))?
+        }
+        Ok(())
+    }
+
+    pub fn calc_options(&self) -> Result<CalcOptions, Box<dyn Error>> {
+        let default = CalcOptions::default();
+        let options = CalcOptions {
+            grid_start: self.values.grid_start.unwrap_or(default.grid_start),
+            grid_end: self.values.grid_end.unwrap_or(default.grid_end),
+            grid_step: self.values.grid_step.unwrap_or(default.grid_step),
+            window: self.values.window.unwrap_or(default.window),
+            estimator: match &self.values.estimator {
+                Some(estimator) => estimator.parse()?,
+                None => default.estimator,
+            },
+            constraints: Constraints {
+                min: (self.values.min_x, self.values.min_y),
+                max: (self.values.max_x, self.values.max_y),
+                max_step: self.values.max_step,
+                monotonic: self
+                    .values
+                    .monotonic
+                    .unwrap_or(default.constraints.monotonic),
+            },
+        };
+        if options.grid_step == 0 || options.grid_start > options.grid_end {
+            Err(format!(
+                "Invalid grid {}..{} step {}",
+                options.grid_start, options.grid_end, options.grid_step
+            ))?
+        }
+        options.constraints.check()?;
+        Ok(options)
+    }
+
+    pub fn plot_options(&self) -> PlotOptions {
+        let mut options = PlotOptions::default();
+        let values = self.values.clone();
+
+        options.tolerance = values.tolerance.unwrap_or(options.tolerance);
+        // правило проверяется при загрузке настроек
+        if let Some(switching) = values.switching.and_then(|s| s.parse().ok()) {
+            options.switching = switching;
+        }
+        options.font = values.font.unwrap_or(options.font);
+        options.axis_names = (
+            values.x_axis_name.unwrap_or(options.axis_names.0),
+            values.y_axis_name.unwrap_or(options.axis_names.1),
+        );
+        options.resolution = (
+            values.plot_width.unwrap_or(options.resolution.0),
+            values.plot_height.unwrap_or(options.resolution.1),
+        );
+        options.dpi = values.dpi.unwrap_or(options.dpi);
+        options
+    }
+
+    /// априорная модель, если она задана
+    pub fn prior(&self) -> Result<Option<Prior>, Box<dyn Error>> {
+        match &self.values.prior {
+            Some(path) => Ok(Some(Prior::from_path(
+                path,
+                self.values.prior_weight.unwrap_or(DEFAULT_PRIOR_WEIGHT),
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    /// история калибровок, если задана её папка
+    pub fn history(&self) -> Option<History> {
+        self.values.history.as_ref().map(History::new)
+    }
+
+    /// вес точек прежних сегментов относительно новых, 0..1
+    pub fn old_weight(&self) -> f64 {
+        self.values.old_weight.unwrap_or(DEFAULT_OLD_WEIGHT) as f64 / 100.0
+    }
+
+    pub fn plan_options(&self) -> PlanOptions {
+        let default = PlanOptions::default();
+        PlanOptions {
+            min_points: self
+                .values
+                .min_points
+                .map_or(default.min_points, |points| points as usize),
+            max_spread: self.values.max_spread.unwrap_or(default.max_spread),
+            settle_minutes: self.values.settle_minutes.unwrap_or(default.settle_minutes),
+            sample_minutes: self.values.sample_minutes.unwrap_or(default.sample_minutes),
+            ramp_rate: self.values.ramp_rate.unwrap_or(default.ramp_rate),
+        }
+    }
+
+    pub fn names(&self) -> NameTemplates {
+        let mut names = NameTemplates::default();
+        if let Some(ct_name) = &self.values.ct_name {
+            names.ct = ct_name.to_owned();
+        }
+        names
+    }
+}
+
+/// общесистемный файл настроек
+pub fn system_path() -> PathBuf {
+    let root = if cfg!(windows) {
+        std::env::var_os("PROGRAMDATA")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+    } else {
+        PathBuf::from("/etc")
+    };
+    root.join(env!("CARGO_PKG_NAME")).join("config.toml")
+}
+
+/// существующие файлы настроек: общесистемный, проектный в папке `project_dir`
+/// и явно указанный
+pub fn files(project_dir: &Path, explicit: Option<&Path>) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files: Vec<PathBuf> = [system_path(), project_dir.join(PROJECT_CONFIG)]
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect();
+
+    if let Some(path) = explicit {
+        if !path.is_file() {
+            Err(format!("Config file {} not found", path.display()))?
+        }
+        files.push(path.to_owned());
+    }
+    Ok(files)
+}
+
+/// разбор переопределения `ключ=значение`, строки можно не заключать в кавычки
+fn parse_override(item: &str) -> Result<Profile, Box<dyn Error>> {
+    let (key, value) = item
+        .split_once('=')
+        .ok_or(format!("Invalid setting `{item}`, expected KEY=VALUE"))?;
+    let (key, value) = (key.trim(), value.trim());
+
+    toml::from_str(&format!("{key} = {value}"))
+        .or_else(|_| toml::from_str(&format!("{key} = {value:?}")))
+        .map_err(|err| format!("Invalid setting `{item}`: {err}").into())
+}
+
+#[test]
+fn layered_settings() {
+    let dir = std::env::temp_dir().join(format!("r-approx-config-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let system = dir.join("system.toml");
+    let project = dir.join("project.toml");
+    std::fs::write(
+        &system,
+        "profile = \"wide\"\n[profiles.default]\nwindow = 2\ntolerance = 2\n[profiles.wide]\ngrid_step = 10\n",
+    )
+    .unwrap();
+    std::fs::write(
+        &project,
+        "[profiles.wide]\nx_axis_name = \"AZ\"\n[profiles.narrow]\ngrid_step = 3\n",
+    )
+    .unwrap();
+    let files = [system, project];
+
+    let settings = Settings::load(&files, None, &[]).unwrap();
+    assert_eq!(settings.profile, "wide");
+    let calc = settings.calc_options().unwrap();
+    assert_eq!((calc.grid_step, calc.window), (10, 2));
+    let plot = settings.plot_options();
+    assert_eq!(plot.axis_names.0, "AZ");
+    assert_eq!(plot.tolerance, 2);
+
+    let settings = Settings::load(
+        &files,
+        Some("narrow"),
+        &["window=1".to_string(), "estimator=mean".to_string()],
+    )
+    .unwrap();
+    let calc = settings.calc_options().unwrap();
+    assert_eq!((calc.grid_step, calc.window), (3, 1));
+    assert_eq!(settings.plot_options().axis_names.0, "ГН");
+    assert!(settings.to_string().contains("estimator = \"mean\""));
+    assert!(settings.prior().unwrap().is_none());
+
+    let settings = Settings::load(
+        &files,
+        None,
+        &[
+            "prior=test/test_data_auto_model.txt".to_string(),
+            "prior_weight=5".to_string(),
+        ],
+    )
+    .unwrap();
+    assert_eq!(settings.prior().unwrap().unwrap().weight, 5);
+
+    let settings = Settings::load(&files, None, &["switching=hysteresis:1".to_string()]).unwrap();
+    assert_eq!(settings.plot_options().switching, Switching::Hysteresis(1));
+    assert!(Settings::load(&files, None, &["switching=floor".to_string()]).is_err());
+
+    let settings = Settings::load(
+        &files,
+        None,
+        &[
+            "max_x=10".to_string(),
+            "max_step=2".to_string(),
+            "monotonic=true".to_string(),
+        ],
+    )
+    .unwrap();
+    let constraints = settings.calc_options().unwrap().constraints;
+    assert_eq!(
+        (constraints.max, constraints.max_step),
+        ((Some(10), None), Some(2))
+    );
+    assert!(constraints.monotonic);
+    assert!(Settings::load(
+        &files,
+        None,
+        &["min_y=5".to_string(), "max_y=-5".to_string()]
+    )
+    .is_err());
+
+    for invalid in [
+        "window=-1",
+        "grid_step=0",
+        "plot_width=0",
+        "dpi=0",
+        "old_weight=150",
+    ] {
+        assert!(Settings::load(&files, None, &[invalid.to_string()]).is_err());
+    }
+    // отрицательное окно в профиле файла, а не только в ключе командной строки
+    let negative = dir.join("negative.toml");
+    std::fs::write(&negative, "[profiles.default]\nwindow = -2\n").unwrap();
+    assert!(Settings::load(&[negative], None, &[]).is_err());
+
+    // пути в файле настроек отсчитываются от его папки, а в ключах — от текущей
+    let nested = dir.join("project");
+    std::fs::create_dir_all(&nested).unwrap();
+    let config = [nested.join(PROJECT_CONFIG)];
+    std::fs::write(
+        &config[0],
+        "[profiles.default]\nprior = \"fleet.txt\"\nhistory = \"calibrations\"\n",
+    )
+    .unwrap();
+    let settings = Settings::load(&config, None, &[]).unwrap();
+    assert_eq!(
+        settings.values.prior.as_deref().map(Path::new),
+        Some(nested.join("fleet.txt").as_path())
+    );
+    assert_eq!(
+        settings.values.history.as_deref().map(Path::new),
+        Some(nested.join("calibrations").as_path())
+    );
+    let settings = Settings::load(&config, None, &["prior=fleet.txt".to_string()]).unwrap();
+    assert_eq!(settings.values.prior.as_deref(), Some("fleet.txt"));
+
+    assert!(Settings::load(&files, Some("unknown"), &[]).is_err());
+    assert!(Settings::load(&files, None, &["estimator=mode".to_string()]).is_err());
+    assert!(Settings::load(&files, None, &["colour=red".to_string()]).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,149 @@
+//! Загрузка таблицы коррекции в изделие с чтением и сверкой
+//!
+//! Команды линии связи: `SERIAL?` → серийный номер, `TABLE CLEAR`, `TABLE SET <temp> <dx> <dy>`
+//! и `TABLE COMMIT` → `OK`, `TABLE SIZE?` → число узлов, `TABLE? <номер>` → `<temp> <dx> <dy>`.
+
+pub mod mock;
+
+use std::error::Error;
+
+pub use crate::link::Link;
+use crate::thermo_model::{DataFrame, DataRow};
+
+/// Изделие на линии связи
+pub struct Device(pub Link);
+
+impl Device {
+    pub fn serial_number(&mut self) -> Result<String, Box<dyn Error>> {
+        self.0.query("SERIAL?")
+    }
+
+    /// запись таблицы `table` и её сохранение в изделии
+    pub fn upload(&mut self, table: &DataFrame) -> Result<(), Box<dyn Error>> {
+        self.0.query("TABLE CLEAR")?;
+        for row in table.rows.iter() {
+            if let (Some(temp), Some(x), Some(y)) = (row.temp, row.x, row.y) {
+                self.0.query(&format!("TABLE SET {temp} {x} {y}"))?;
+            }
+        }
+        self.0.query("TABLE COMMIT")?;
+        Ok(())
+    }
+
+    /// чтение таблицы из изделия
+    pub fn read_table(&mut self) -> Result<DataFrame, Box<dyn Error>> {
+        let reply = self.0.query("TABLE SIZE?")?;
+        let size: usize = reply
+            .parse()
+            .map_err(|_| format!("Invalid table size `{reply}`"))?;
+
+        let mut table = DataFrame::default();
+        for i in 0..size {
+            let reply = self.0.query(&format!("TABLE? {i}"))?;
+            let values = reply
+                .split_whitespace()
+                .map(|value| value.parse::<i32>())
+                .collect::<Result<Vec<_>, _>>();
+            match values.as_deref() {
+                Ok([temp, x, y]) => table.rows.push(DataRow {
+                    temp: Some(*temp),
+                    x: Some(*x),
+                    y: Some(*y),
+                }),
+                _ => Err(format!("Invalid table row {i} `{reply}`"))?,
+            }
+        }
+        Ok(table)
+    }
+}
+
+/// Расхождение узла таблицы в изделии с рассчитанной таблицей
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mismatch {
+    pub temp: i32,
+    /// значение в рассчитанной таблице, `None` — лишний узел в изделии
+    pub expected: Option<(i32, i32)>,
+    /// значение в изделии, `None` — узел не записан
+    pub actual: Option<(i32, i32)>,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let value = |value: Option<(i32, i32)>| {
+            value.map_or("none".to_string(), |(x, y)| format!("{x};{y}"))
+        };
+        write!(
+            f,
+            "node {}: expected {}, read {}",
+            self.temp,
+            value(self.expected),
+            value(self.actual)
+        )
+    }
+}
+
+/// сверка прочитанной из изделия таблицы `actual` с рассчитанной `expected` по узлам
+pub fn compare(expected: &DataFrame, actual: &DataFrame) -> Vec<Mismatch> {
+    let values = |table: &DataFrame| -> Vec<(i32, (i32, i32))> {
+        table
+            .rows
+            .iter()
+            .filter_map(|row| Some((row.temp?, (row.x?, row.y?))))
+            .collect()
+    };
+    let (expected, actual) = (values(expected), values(actual));
+    let find = |table: &[(i32, (i32, i32))], temp: i32| {
+        table
+            .iter()
+            .find(|(node, _)| *node == temp)
+            .map(|(_, value)| *value)
+    };
+
+    let mut temps: Vec<i32> = expected
+        .iter()
+        .chain(actual.iter())
+        .map(|(temp, _)| *temp)
+        .collect();
+    temps.sort_unstable();
+    temps.dedup();
+    temps
+        .into_iter()
+        .map(|temp| Mismatch {
+            temp,
+            expected: find(&expected, temp),
+            actual: find(&actual, temp),
+        })
+        .filter(|item| item.expected != item.actual)
+        .collect()
+}
+
+#[test]
+fn upload_and_verify() {
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let mut device = mock::MockDevice::new("2БЛ1");
+    device.corrupt = Some(52);
+    std::thread::spawn(move || mock::serve(listener, device).is_ok());
+
+    let table: DataFrame = "temp;dx;dy\r\n46;1;-18\r\n52;2;-17\r\n58;2;-16"
+        .parse()
+        .unwrap();
+    let mut device = Device(Link::connect(&address, Duration::from_secs(5)).unwrap());
+    assert_eq!(device.serial_number().unwrap(), "2БЛ1");
+    device.upload(&table).unwrap();
+    let read = device.read_table().unwrap();
+    assert_eq!(read.rows.len(), 3);
+
+    let mismatches = compare(&table, &read);
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(
+        mismatches[0].to_string(),
+        "node 52: expected 2;-17, read 2;-16"
+    );
+    let short: DataFrame = "temp;dx;dy\r\n46;1;-18\r\n52;2;-16".parse().unwrap();
+    assert_eq!(compare(&short, &read)[0].expected, None);
+    assert!(compare(&read, &read).is_empty());
+}
@@ -0,0 +1,108 @@
+//! Имитация изделия для проверки загрузки таблицы без оборудования
+
+use std::error::Error;
+use std::net::TcpListener;
+
+use crate::link;
+
+/// Имитируемое изделие с таблицей коррекции
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MockDevice {
+    pub serial_number: String,
+    /// сохранённая таблица: температура и значения по осям x и y
+    pub table: Vec<(i32, i32, i32)>,
+    /// записываемая таблица до `TABLE COMMIT`
+    pending: Vec<(i32, i32, i32)>,
+    /// узел, который записывается с ошибкой по оси y, для проверки сверки
+    pub corrupt: Option<i32>,
+}
+
+impl MockDevice {
+    pub fn new(serial_number: &str) -> Self {
+        Self {
+            serial_number: serial_number.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// ответ на команду протокола
+    pub fn reply(&mut self, command: &str) -> String {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words[..] {
+            ["*IDN?"] => "r-approx mock tpk-k".to_string(),
+            ["SERIAL?"] => self.serial_number.clone(),
+            ["TABLE", "CLEAR"] => {
+                self.pending.clear();
+                "OK".to_string()
+            }
+            ["TABLE", "SET", temp, x, y] => match (temp.parse(), x.parse(), y.parse()) {
+                (Ok(temp), Ok(x), Ok(y)) => {
+                    let y = if self.corrupt == Some(temp) { y + 1 } else { y };
+                    self.pending.push((temp, x, y));
+                    "OK".to_string()
+                }
+                _ => format!("ERR invalid row `{command}`"),
+            },
+            ["TABLE", "COMMIT"] => {
+                self.table = std::mem::take(&mut self.pending);
+                "OK".to_string()
+            }
+            ["TABLE", "SIZE?"] => self.table.len().to_string(),
+            ["TABLE?", index] => {
+                match index.parse::<usize>().ok().and_then(|i| self.table.get(i)) {
+                    Some((temp, x, y)) => format!("{temp} {x} {y}"),
+                    None => format!("ERR no row `{index}`"),
+                }
+            }
+            _ => format!("ERR unknown command `{command}`"),
+        }
+    }
+}
+
+/// обслуживание подключений к `listener`, каждое в своём потоке с общим изделием `device`
+pub fn serve(listener: TcpListener, device: MockDevice) -> Result<(), Box<dyn Error>> {
+    link::serve(listener, device, MockDevice::reply)
+}
+
+/// имитация изделия на псевдотерминале: `ready` получает путь к порту для подключения,
+/// ответы выдаются до остановки процесса
+#[cfg(unix)]
+pub fn serve_pty<F: FnOnce(&str)>(mut device: MockDevice, ready: F) -> Result<(), Box<dyn Error>> {
+    use serialport::SerialPort;
+
+    let (mut master, slave) = serialport::TTYPort::pair()?;
+    master.set_timeout(std::time::Duration::from_secs(3600))?;
+    ready(&slave.name().ok_or("Unknown pseudo terminal name")?);
+    // порт программы загрузки открывается монопольно, поэтому имитация его не держит
+    drop(slave);
+    loop {
+        let result = link::answer(master.try_clone_native()?, &mut master, |command| {
+            Ok(device.reply(command))
+        });
+        let kind = match &result {
+            Err(err) => err.downcast_ref::<std::io::Error>().map(|err| err.kind()),
+            Ok(()) => None,
+        };
+        match kind {
+            // тайм-аут только прерывает ожидание команды
+            Some(std::io::ErrorKind::TimedOut) => {}
+            // порт никем не открыт: ожидание следующего подключения
+            Some(std::io::ErrorKind::BrokenPipe) => {
+                std::thread::sleep(std::time::Duration::from_millis(100))
+            }
+            _ => return result,
+        }
+    }
+}
+
+#[test]
+fn mock_table() {
+    let mut device = MockDevice::new("2БЛ1");
+    assert_eq!(device.reply("SERIAL?"), "2БЛ1");
+    assert_eq!(device.reply("TABLE SET 46 1 -18"), "OK");
+    assert_eq!(device.reply("TABLE SIZE?"), "0");
+    assert_eq!(device.reply("TABLE COMMIT"), "OK");
+    assert_eq!(device.reply("TABLE? 0"), "46 1 -18");
+    assert!(device.reply("TABLE? 1").starts_with("ERR"));
+    assert!(device.reply("TABLE SET 46 1").starts_with("ERR"));
+}
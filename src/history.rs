@@ -0,0 +1,365 @@
+//! История калибровок: папка с записью на каждый расчёт
+//!
+//! Записи хранятся в папках `<root>/<серийный номер>/<дата>/`, в каждой лежит
+//! описание `record.toml`, рассчитанная модель `model.txt` и копии входных файлов.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::batch::DeviceReport;
+use crate::thermo_model::{
+    plot_overlay, Curve, DataFrame, DataRow, ModelDiff, Overlay, PlotOptions,
+};
+
+const RECORD_FILE: &str = "record.toml";
+const MODEL_FILE: &str = "model.txt";
+const INPUTS_DIR: &str = "inputs";
+
+/// Файл расчёта и его хэш
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileRecord {
+    pub path: String,
+    pub sha256: String,
+}
+
+impl FileRecord {
+    pub fn new(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            path: path.display().to_string(),
+            sha256: sha256(path)?,
+        })
+    }
+}
+
+/// Запись о калибровке
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub serial: String,
+    /// дата расчёта в формате RFC 3339
+    pub date: String,
+    /// `predict` или `validate`
+    pub command: String,
+    pub verdict: String,
+    /// число узлов сетки с данными и общее число узлов
+    pub coverage: String,
+    pub worst_residual: Option<i32>,
+    pub message: String,
+    pub inputs: Vec<FileRecord>,
+    pub outputs: Vec<FileRecord>,
+}
+
+impl Record {
+    /// запись по итогу расчёта `report`, хэши считаются по текущему содержимому файлов
+    pub fn new(
+        report: &DeviceReport,
+        command: &str,
+        date: DateTime<Local>,
+        inputs: &[PathBuf],
+        outputs: &[PathBuf],
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            serial: report.serial.clone(),
+            date: date.to_rfc3339(),
+            command: command.to_string(),
+            verdict: report.verdict.to_string(),
+            coverage: format!("{}/{}", report.coverage.0, report.coverage.1),
+            worst_residual: report.worst_residual,
+            message: report.message.clone(),
+            inputs: files(inputs)?,
+            outputs: files(outputs)?,
+        })
+    }
+
+    pub fn date(&self) -> Result<DateTime<Local>, Box<dyn Error>> {
+        Ok(DateTime::parse_from_rfc3339(&self.date)?.with_timezone(&Local))
+    }
+}
+
+impl std::fmt::Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let date = self
+            .date()
+            .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|_| self.date.clone());
+        write!(
+            f,
+            "{date}  {:12} {:8} {:5} {:>7} {:>6}",
+            self.serial,
+            self.command,
+            self.verdict,
+            self.coverage,
+            self.worst_residual
+                .map_or("-".to_string(), |worst| worst.to_string())
+        )?;
+        if let Some(input) = self.inputs.first() {
+            write!(f, "  {}", input.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Запись в истории и её папка
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub dir: PathBuf,
+    pub record: Record,
+}
+
+impl Entry {
+    /// модель, рассчитанная при калибровке
+    pub fn model(&self) -> Result<DataFrame, Box<dyn Error>> {
+        DataFrame::from_path(self.dir.join(MODEL_FILE))
+    }
+}
+
+/// История калибровок в папке `root`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History {
+    pub root: PathBuf,
+}
+
+impl History {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// добавление записи с моделью `model`, входные файлы копируются в запись
+    pub fn add(&self, record: &Record, model: &DataFrame) -> Result<PathBuf, Box<dyn Error>> {
+        if record.serial.is_empty() {
+            Err("Serial number is required for the calibration history")?
+        }
+        let serial_dir = self.root.join(record.serial.replace(['/', '\\', ':'], "_"));
+        std::fs::create_dir_all(&serial_dir)?;
+
+        let name = record.date()?.format("%Y-%m-%d_%H-%M-%S").to_string();
+        let mut dir = serial_dir.join(&name);
+        let mut index = 1;
+        while let Err(err) = std::fs::create_dir(&dir) {
+            if err.kind() != std::io::ErrorKind::AlreadyExists {
+                Err(err)?
+            }
+            dir = serial_dir.join(format!("{name}_{index}"));
+            index += 1;
+        }
+
+        // копии нумеруются в порядке записи, чтобы одноимённые файлы из разных папок
+        // не затирали друг друга
+        std::fs::create_dir(dir.join(INPUTS_DIR))?;
+        for (i, input) in record.inputs.iter().enumerate() {
+            let path = Path::new(&input.path);
+            if let Some(name) = path.file_name() {
+                let mut copy = OsString::from(format!("{}_", i + 1));
+                copy.push(name);
+                std::fs::copy(path, dir.join(INPUTS_DIR).join(copy))?;
+            }
+        }
+        model.save_file(dir.join(MODEL_FILE))?;
+        std::fs::write(
+            dir.join(RECORD_FILE),
+            toml::to_string(&toml::Value::try_from(record)?)?,
+        )?;
+
+        Ok(dir)
+    }
+
+    /// записи по серийному номеру `serial` или все записи, по возрастанию даты
+    pub fn entries(&self, serial: Option<&str>) -> Result<Vec<Entry>, Box<dyn Error>> {
+        let mut entries = vec![];
+        if !self.root.is_dir() {
+            return Ok(entries);
+        }
+
+        for serial_dir in std::fs::read_dir(&self.root)? {
+            let serial_dir = serial_dir?.path();
+            if !serial_dir.is_dir() {
+                continue;
+            }
+            for dir in std::fs::read_dir(&serial_dir)? {
+                let dir = dir?.path();
+                let path = dir.join(RECORD_FILE);
+                if !path.is_file() {
+                    continue;
+                }
+                let record: Record = toml::from_str(&std::fs::read_to_string(&path)?)
+                    .map_err(|err| format!("Invalid record {}: {err}", path.display()))?;
+                if serial.map_or(true, |serial| record.serial == serial) {
+                    entries.push(Entry { dir, record });
+                }
+            }
+        }
+
+        entries.sort_by_key(|entry| {
+            (
+                entry.record.date().ok(),
+                entry.record.serial.clone(),
+                entry.dir.clone(),
+            )
+        });
+        Ok(entries)
+    }
+}
+
+/// изменения модели между соседними калибровками и за всё время
+pub fn drift(entries: &[Entry]) -> Result<Vec<(String, ModelDiff)>, Box<dyn Error>> {
+    let models = entries
+        .iter()
+        .map(|entry| entry.model())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut diffs = vec![];
+    for i in 1..models.len() {
+        diffs.push((
+            format!("{} → {}", label(&entries[i - 1]), label(&entries[i])),
+            ModelDiff::new(&models[i - 1], &models[i], Some(0)),
+        ));
+    }
+    if models.len() > 2 {
+        diffs.push((
+            format!(
+                "{} → {}",
+                label(&entries[0]),
+                label(&entries[models.len() - 1])
+            ),
+            ModelDiff::new(&models[0], &models[models.len() - 1], Some(0)),
+        ));
+    }
+    Ok(diffs)
+}
+
+/// график моделей всех калибровок с подписью по дате
+pub fn drift_plot(
+    entries: &[Entry],
+    path: &Path,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn Error>> {
+    let models = entries
+        .iter()
+        .map(|entry| Ok((label(entry), entry.model()?)))
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+    let overlay = |value: fn(&DataRow) -> Option<i32>| Overlay {
+        curves: models
+            .iter()
+            .map(|(label, model)| Curve {
+                label: label.clone(),
+                points: model
+                    .rows
+                    .iter()
+                    .filter_map(|row| Some((row.temp?, value(row)?)))
+                    .collect(),
+                highlighted: true,
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    let serial = entries
+        .first()
+        .map(|entry| entry.record.serial.as_str())
+        .unwrap_or_default();
+    plot_overlay(
+        path,
+        options,
+        &format!("{serial}: {} calibrations", entries.len()),
+        overlay(|row| row.x),
+        overlay(|row| row.y),
+    )
+}
+
+/// подпись калибровки: дата без секунд
+fn label(entry: &Entry) -> String {
+    entry
+        .record
+        .date()
+        .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| entry.record.date.clone())
+}
+
+fn files(paths: &[PathBuf]) -> Result<Vec<FileRecord>, Box<dyn Error>> {
+    paths.iter().map(|path| FileRecord::new(path)).collect()
+}
+
+/// хэш SHA-256 содержимого файла
+pub fn sha256(path: &Path) -> Result<String, Box<dyn Error>> {
+    let digest = Sha256::digest(std::fs::read(path)?);
+    Ok(digest.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+#[test]
+fn history_store() {
+    use chrono::TimeZone;
+
+    let root = std::env::temp_dir().join(format!("r-approx-history-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let history = History::new(&root);
+    assert!(history.entries(None).unwrap().is_empty());
+
+    let input = root.join("data.csv");
+    let inputs = [input.clone()];
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(&input, "temp;dx;dy\r\n0;0;0").unwrap();
+    let abc = root.join("abc.txt");
+    std::fs::write(&abc, "abc").unwrap();
+    assert_eq!(
+        sha256(&abc).unwrap(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+
+    let mut report = DeviceReport::error("2БЛ123", &input, "");
+    report.verdict = crate::batch::Verdict::Pass;
+    report.coverage = (2, 2);
+    report.worst_residual = Some(1);
+
+    let models: [DataFrame; 3] = [
+        "temp;dx;dy\r\n0;1;1\r\n6;2;2".parse().unwrap(),
+        "temp;dx;dy\r\n0;1;2\r\n6;2;2".parse().unwrap(),
+        "temp;dx;dy\r\n0;1;4\r\n6;2;2".parse().unwrap(),
+    ];
+    for (day, model) in [3, 1, 2].iter().zip(models.iter()) {
+        let date = Local.with_ymd_and_hms(2024, 5, *day, 10, 0, 0).unwrap();
+        let record = Record::new(&report, "predict", date, &inputs, &[]).unwrap();
+        history.add(&record, model).unwrap();
+    }
+    let date = Local.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap();
+    let mut record = Record::new(&report, "validate", date, &inputs, &[]).unwrap();
+    history.add(&record, &models[0]).unwrap();
+    record.serial = "2БЛ124".to_string();
+    history.add(&record, &models[0]).unwrap();
+
+    assert_eq!(history.entries(None).unwrap().len(), 5);
+    let entries = history.entries(Some("2БЛ123")).unwrap();
+    assert_eq!(entries.len(), 4);
+    assert!(entries[0].record.date < entries[3].record.date);
+    assert!(entries[1].dir.ends_with("2024-05-01_10-00-00_1"));
+    assert!(entries[0].dir.join("inputs/1_data.csv").is_file());
+    assert_eq!(entries[0].record.inputs[0].sha256, sha256(&input).unwrap());
+    assert_eq!(entries[3].model().unwrap(), models[0]);
+
+    let drift = drift(&entries[1..]).unwrap();
+    assert_eq!(drift.len(), 3);
+    assert_eq!(drift[0].1.max_change(), 3);
+    assert_eq!(drift[2].1.max_change(), 0);
+
+    // одноимённые входные файлы из разных папок сохраняются оба
+    let runs = [root.join("run1/data.csv"), root.join("run2/data.csv")];
+    for (i, run) in runs.iter().enumerate() {
+        std::fs::create_dir_all(run.parent().unwrap()).unwrap();
+        std::fs::write(run, format!("temp;dx;dy\r\n0;{i};0")).unwrap();
+    }
+    record.serial = "2БЛ125".to_string();
+    record.inputs = runs
+        .iter()
+        .map(|run| FileRecord::new(run).unwrap())
+        .collect();
+    let dir = history.add(&record, &models[0]).unwrap();
+    for (i, run) in runs.iter().enumerate() {
+        let copy = dir.join(format!("inputs/{}_data.csv", i + 1));
+        assert_eq!(sha256(&copy).unwrap(), sha256(run).unwrap());
+    }
+    std::fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,23 @@
+//! Расчёт термокомпенсации ТПК-К по сырым данным
+//!
+//! Библиотека не зависит от текущей папки и системного времени: пути, дата расчёта
+//! и параметры передаются явно, данные можно читать и записывать через потоки.
+
+pub mod acquisition;
+pub mod batch;
+pub mod config;
+pub mod device;
+pub mod history;
+pub mod link;
+pub mod synth;
+mod thermo_model;
+pub mod watch;
+
+pub use thermo_model::braille;
+pub use thermo_model::{
+    display_available, parse_resolution, read_profile, Adjustment, Artifact, Bound, CalcOptions,
+    Constraints, DataFrame, DataRow, Direction, ErrorStats, Estimator, Firmware, Fleet, Format,
+    ModelDiff, NameTemplates, NodeDelta, NodeStats, Outlier, Override, Plan, PlanOptions,
+    PlotOptions, Prior, PriorInfluence, Reason, Replay, ReplayPoint, ResidualStats, Segment,
+    Setpoint, Switching, ThermoModel, Update,
+};
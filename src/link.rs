@@ -0,0 +1,130 @@
+//! Текстовая линия связи с устройством по TCP или последовательному порту
+//!
+//! Команда и ответ занимают по строке, ответ `ERR <текст>` означает ошибку устройства.
+
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// скорость последовательного порта по умолчанию
+pub const DEFAULT_BAUD: u32 = 115_200;
+
+/// Текстовое соединение с устройством
+pub struct Link {
+    address: String,
+    reader: BufReader<Box<dyn Read + Send>>,
+    writer: Box<dyn Write + Send>,
+}
+
+impl Link {
+    /// подключение по TCP к `address` вида `host:port`, `timeout` ограничивает и ожидание ответа
+    pub fn connect(address: &str, timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        let socket = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or(format!("Invalid address `{address}`"))?;
+        let stream = TcpStream::connect_timeout(&socket, timeout)
+            .map_err(|err| format!("Can not connect to {address}: {err}"))?;
+        stream.set_read_timeout(Some(timeout))?;
+        Ok(Self {
+            address: address.to_string(),
+            writer: Box::new(stream.try_clone()?),
+            reader: BufReader::new(Box::new(stream)),
+        })
+    }
+
+    /// подключение к последовательному порту `path`, например `/dev/ttyUSB0` или `COM3`
+    pub fn serial(path: &str, baud: u32, timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        let port = serialport::new(path, baud)
+            .timeout(timeout)
+            .open()
+            .map_err(|err| format!("Can not open {path}: {err}"))?;
+        Ok(Self {
+            address: path.to_string(),
+            writer: Box::new(port.try_clone()?),
+            reader: BufReader::new(Box::new(port)),
+        })
+    }
+
+    /// подключение по адресу: `host:port` — TCP, иначе имя последовательного порта
+    pub fn open(address: &str, baud: u32, timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        if is_serial(address) {
+            Self::serial(address, baud, timeout)
+        } else {
+            Self::connect(address, timeout)
+        }
+    }
+
+    /// команда и ответ устройства без перевода строки
+    pub fn query(&mut self, command: &str) -> Result<String, Box<dyn Error>> {
+        self.writer.write_all(format!("{command}\n").as_bytes())?;
+        self.writer.flush()?;
+        let mut reply = String::new();
+        if self.reader.read_line(&mut reply)? == 0 {
+            Err(format!("Connection to {} closed", self.address))?
+        }
+
+        let reply = reply.trim().to_string();
+        if let Some(message) = reply.strip_prefix("ERR") {
+            Err(format!("{} `{command}`: {}", self.address, message.trim()))?
+        }
+        Ok(reply)
+    }
+}
+
+/// ответы `reply` на команды из `reader` до закрытия соединения, для имитаций устройств
+pub fn answer<R, W, F>(reader: R, mut writer: W, mut reply: F) -> Result<(), Box<dyn Error>>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(&str) -> Result<String, Box<dyn Error>>,
+{
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let reply = reply(line.trim())?;
+        writer.write_all(format!("{reply}\n").as_bytes())?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// обслуживание подключений к `listener` имитацией устройства `device`:
+/// каждое подключение в своём потоке, ответы `reply` общего устройства
+pub fn serve<D: Send + 'static>(
+    listener: TcpListener,
+    device: D,
+    reply: fn(&mut D, &str) -> String,
+) -> Result<(), Box<dyn Error>> {
+    let device = Arc::new(Mutex::new(device));
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let device = Arc::clone(&device);
+        std::thread::spawn(move || {
+            let result = stream.try_clone().map_err(Into::into).and_then(|writer| {
+                answer(&stream, writer, |command| {
+                    let mut device = device.lock().map_err(|_| "Mock device is poisoned")?;
+                    Ok(reply(&mut device, command))
+                })
+            });
+            if let Err(err) = result {
+                eprintln!("mock device: {err}");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// адрес последовательного порта: путь к устройству или имя порта Windows
+fn is_serial(address: &str) -> bool {
+    address.starts_with('/') || address.to_uppercase().starts_with("COM")
+}
+
+#[test]
+fn link_addresses() {
+    assert!(is_serial("/dev/ttyUSB0"));
+    assert!(is_serial("COM3"));
+    assert!(!is_serial("127.0.0.1:5025"));
+    assert!(!is_serial("chamber.local:5025"));
+}
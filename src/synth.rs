@@ -0,0 +1,360 @@
+//! Синтетические сырые данные по заданной истинной кривой ухода
+//!
+//! Файл записывается в формате `temp;x;y` с сегментами `# plus ...;;` и `# minus ...;;`, как при
+//! снятии данных: с шумом и дискретностью датчика, тепловым запаздыванием, смещением нуля в
+//! каждом сегменте, пропусками диапазонов и выбросами. По таким файлам проверяется, насколько
+//! точно расчёт восстанавливает исходную кривую.
+
+use std::error::Error;
+
+use crate::thermo_model::{DataFrame, DataRow};
+
+/// температура, при которой кривая по умолчанию равна нулю
+const REFERENCE_TEMP: f64 = 20.0;
+
+/// Истинная кривая ухода: узлы `(temp, x, y)` с линейной интерполяцией между ними,
+/// за крайними узлами значение постоянно
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrueCurve {
+    pub nodes: Vec<(f64, f64, f64)>,
+}
+
+/// плавная кривая с нулём при 20 °C и размахом в несколько единиц, как у реальных изделий
+impl Default for TrueCurve {
+    fn default() -> Self {
+        let nodes = (-60..=80)
+            .step_by(5)
+            .map(|temp| {
+                let delta = temp as f64 - REFERENCE_TEMP;
+                (
+                    temp as f64,
+                    -0.04 * delta - 0.0003 * delta * delta,
+                    0.05 * delta + 0.0004 * delta * delta,
+                )
+            })
+            .collect();
+        Self { nodes }
+    }
+}
+
+impl TrueCurve {
+    /// кривая по узлам таблицы, например файла модели
+    pub fn from_frame(frame: &DataFrame) -> Result<Self, Box<dyn Error>> {
+        let nodes: Vec<_> = frame
+            .rows
+            .iter()
+            .filter_map(|row| Some((row.temp? as f64, row.x? as f64, row.y? as f64)))
+            .collect();
+        if nodes.is_empty() {
+            Err("Empty drift curve")?
+        }
+        Ok(Self { nodes })
+    }
+
+    /// значение кривой при температуре `temp`
+    pub fn eval(&self, temp: f64) -> (f64, f64) {
+        let (Some(first), Some(last)) = (self.nodes.first(), self.nodes.last()) else {
+            return (0.0, 0.0);
+        };
+        if temp <= first.0 {
+            return (first.1, first.2);
+        }
+        for pair in self.nodes.windows(2) {
+            let ((t0, x0, y0), (t1, x1, y1)) = (pair[0], pair[1]);
+            if temp <= t1 {
+                let k = if t1 > t0 {
+                    (temp - t0) / (t1 - t0)
+                } else {
+                    1.0
+                };
+                return (x0 + k * (x1 - x0), y0 + k * (y1 - y0));
+            }
+        }
+        (last.1, last.2)
+    }
+
+    /// ошибки модели `calc` в её узлах относительно кривой: в абсолютных координатах каждый
+    /// сегмент сырых данных `raw` отсчитывается от своей первой точки, поэтому истинное
+    /// значение узла — среднее по сегментам с точками в окне `window`, взвешенное числом точек
+    pub fn errors(&self, calc: &DataFrame, raw: &DataFrame, window: i32) -> DataFrame {
+        // начало отсчёта и число точек в окне узла для каждого сегмента
+        let references: Vec<(f64, f64)> = raw
+            .segments
+            .iter()
+            .filter_map(|segment| Some(self.eval(segment.rows.first()?.temp? as f64)))
+            .collect();
+        let mut item = DataFrame::default();
+        for row in calc.rows.iter() {
+            if let (Some(temp), Some(x), Some(y)) = (row.temp, row.x, row.y) {
+                let counts: Vec<f64> = raw
+                    .segments
+                    .iter()
+                    .filter(|segment| !segment.rows.is_empty())
+                    .map(|segment| {
+                        segment
+                            .rows
+                            .iter()
+                            .filter(|row| row.temp.map_or(false, |t| (t - temp).abs() <= window))
+                            .count() as f64
+                    })
+                    .collect();
+                let total: f64 = counts.iter().sum();
+                // узел без точек получает значение соседних, отсчёт от первого сегмента
+                let weights: Vec<f64> = match total > 0.0 {
+                    true => counts.iter().map(|count| count / total).collect(),
+                    false => (0..references.len())
+                        .map(|i| (i == 0) as u8 as f64)
+                        .collect(),
+                };
+                let zero = references
+                    .iter()
+                    .zip(weights.iter())
+                    .fold((0.0, 0.0), |(x, y), ((x0, y0), weight)| {
+                        (x + x0 * weight, y + y0 * weight)
+                    });
+
+                let (true_x, true_y) = self.eval(temp as f64);
+                item.rows.push(DataRow {
+                    temp: Some(temp),
+                    x: Some(x - (true_x - zero.0).round() as i32),
+                    y: Some(y - (true_y - zero.1).round() as i32),
+                });
+            }
+        }
+        item
+    }
+}
+
+/// Параметры синтетических данных
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynthOptions {
+    /// сегменты `(начальная, конечная температура)`, направление по знаку разности
+    pub segments: Vec<(i32, i32)>,
+    /// шаг температуры между точками, °C
+    pub step: usize,
+    /// число точек на каждой температуре
+    pub points: usize,
+    /// СКО нормального шума положения
+    pub noise: f64,
+    /// дискретность показаний датчика положения
+    pub quantum: u32,
+    /// тепловое запаздывание, °C: при нагреве положение соответствует температуре ниже
+    /// показания камеры, при охлаждении — выше
+    pub hysteresis: f64,
+    /// наибольшее смещение нуля датчика в сегменте
+    pub baseline: u32,
+    /// диапазоны температур без точек
+    pub missing: Vec<(i32, i32)>,
+    /// доля точек-выбросов
+    pub outlier_rate: f64,
+    /// величина выброса
+    pub outlier_size: i32,
+    /// подпись сегментов после направления
+    pub label: String,
+    /// начальное значение генератора случайных чисел, один и тот же файл при равных значениях
+    pub seed: u64,
+}
+
+impl Default for SynthOptions {
+    fn default() -> Self {
+        Self {
+            segments: vec![(20, 70), (20, -50)],
+            step: 2,
+            points: 1,
+            noise: 0.5,
+            quantum: 1,
+            hysteresis: 1.0,
+            baseline: 20,
+            missing: vec![],
+            outlier_rate: 0.02,
+            outlier_size: 8,
+            label: "synthetic".to_string(),
+            seed: 1,
+        }
+    }
+}
+
+/// сырые данные в абсолютных координатах по кривой `curve`
+pub fn generate(curve: &TrueCurve, options: &SynthOptions) -> Result<String, Box<dyn Error>> {
+    let mut random = Random(options.seed.max(1));
+    let quantum = options.quantum.max(1) as f64;
+    let mut table = "temp;x;y\r\n".to_string();
+    let mut empty = true;
+
+    for (start, end) in options.segments.iter().copied() {
+        let (name, lag) = if end < start {
+            ("minus", options.hysteresis)
+        } else {
+            ("plus", -options.hysteresis)
+        };
+        let comment = format!("{name} {}", options.label);
+        table += &format!("# {};;\r\n", comment.trim_end());
+
+        let baseline = options.baseline as f64;
+        let zero = (
+            (random.uniform() * 2.0 - 1.0) * baseline,
+            (random.uniform() * 2.0 - 1.0) * baseline,
+        );
+        let temps: Vec<i32> = if start <= end {
+            (start..=end).step_by(options.step.max(1)).collect()
+        } else {
+            (end..=start).rev().step_by(options.step.max(1)).collect()
+        };
+        for temp in temps {
+            let missing = options
+                .missing
+                .iter()
+                .any(|(a, b)| *a.min(b) <= temp && temp <= *a.max(b));
+            if missing {
+                continue;
+            }
+            let (x, y) = curve.eval(temp as f64 + lag);
+            for _ in 0..options.points {
+                let mut read = |value: f64| {
+                    let mut value = value + random.normal() * options.noise;
+                    if random.uniform() < options.outlier_rate {
+                        let sign = if random.uniform() < 0.5 { -1.0 } else { 1.0 };
+                        value += sign * options.outlier_size as f64;
+                    }
+                    ((value / quantum).round() * quantum) as i32
+                };
+                let (x, y) = (read(x + zero.0), read(y + zero.1));
+                table += &format!("{temp};{x};{y}\r\n");
+                empty = false;
+            }
+        }
+    }
+    if empty {
+        Err("No points to generate, all temperatures are missing")?
+    }
+    Ok(table)
+}
+
+/// Генератор псевдослучайных чисел xorshift, воспроизводимый от запуска к запуску
+struct Random(u64);
+
+impl Random {
+    /// равномерное на `[0, 1)`
+    fn uniform(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// стандартное нормальное, преобразование Бокса — Мюллера
+    fn normal(&mut self) -> f64 {
+        let (u, v) = (1.0 - self.uniform(), self.uniform());
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+}
+
+/// диапазоны температур из строки вида `20:70,20:-50`
+pub fn parse_ranges(s: &str) -> Result<Vec<(i32, i32)>, Box<dyn Error>> {
+    let invalid = || format!("Invalid ranges `{s}`, expected START:END,...");
+    s.split(',')
+        .filter(|range| !range.trim().is_empty())
+        .map(|range| match range.split(':').collect::<Vec<_>>()[..] {
+            [start, end] => Ok((
+                start.trim().parse().map_err(|_| invalid())?,
+                end.trim().parse().map_err(|_| invalid())?,
+            )),
+            _ => Err(invalid().into()),
+        })
+        .collect()
+}
+
+#[test]
+fn synthetic_recovery() {
+    use crate::thermo_model::{CalcOptions, Direction, Estimator, ResidualStats};
+
+    let curve = TrueCurve::default();
+    assert_eq!(curve.eval(REFERENCE_TEMP), (0.0, 0.0));
+    assert_eq!(curve.eval(-100.0), curve.eval(-60.0));
+    let options = SynthOptions::default();
+    let table = generate(&curve, &options).unwrap();
+    assert_eq!(table, generate(&curve, &options).unwrap());
+
+    let raw: DataFrame = table.parse().unwrap();
+    assert_eq!(raw.segments.len(), 2);
+    assert_eq!(raw.segments[1].direction(), Direction::Minus);
+    assert_eq!(raw.segments[0].rows.len(), 26);
+
+    // регрессия расчёта: медиана восстанавливает кривую с точностью до единицы
+    let stats = |estimator: Estimator, raw: &DataFrame| {
+        let calc = raw.calc(&CalcOptions {
+            estimator,
+            ..Default::default()
+        });
+        let errors = curve.errors(&calc, raw, 3);
+        let values = |value: fn(&DataRow) -> Option<i32>| -> Vec<i32> {
+            errors.rows.iter().filter_map(value).collect()
+        };
+        (
+            ResidualStats::new(&values(|row| row.x), 1),
+            ResidualStats::new(&values(|row| row.y), 1),
+        )
+    };
+    let (x, y) = stats(Estimator::Median, &raw);
+    assert_eq!(x.count, 21);
+    assert!(x.max_abs <= 1 && y.max_abs <= 1, "{x}\r\n{y}");
+
+    // с частыми выбросами медиана устойчивее среднего
+    let noisy: DataFrame = generate(
+        &curve,
+        &SynthOptions {
+            points: 3,
+            outlier_rate: 0.2,
+            outlier_size: 20,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .parse()
+    .unwrap();
+    let (median, mean) = (
+        stats(Estimator::Median, &noisy),
+        stats(Estimator::Mean, &noisy),
+    );
+    // выброс в первой точке сегмента смещает весь сегмент, поэтому допуск шире
+    assert!(median.0.max_abs <= 2 && median.1.max_abs <= 2);
+    assert!(mean.0.max_abs > median.0.max_abs && mean.1.max_abs > median.1.max_abs);
+
+    let gapped: DataFrame = generate(
+        &curve,
+        &SynthOptions {
+            missing: parse_ranges("30:40").unwrap(),
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .parse()
+    .unwrap();
+    assert!(gapped
+        .rows
+        .iter()
+        .all(|row| !(30..=40).contains(&row.temp.unwrap())));
+    assert_eq!(gapped.gaps(&CalcOptions::default(), 1), vec![(34, 34)]);
+    assert!(parse_ranges("30-40").is_err());
+
+    // нагрев и охлаждение от верхней температуры: сегменты отсчитываются от разных точек
+    let exact = SynthOptions {
+        segments: vec![(20, 70), (70, -50)],
+        noise: 0.0,
+        baseline: 0,
+        outlier_rate: 0.0,
+        hysteresis: 0.0,
+        ..Default::default()
+    };
+    let cycle: DataFrame = generate(&curve, &exact).unwrap().parse().unwrap();
+    let (x, y) = stats(Estimator::Median, &cycle);
+    assert!(x.max_abs <= 1 && y.max_abs <= 1, "{x}\r\n{y}");
+    assert!(generate(
+        &curve,
+        &SynthOptions {
+            missing: parse_ranges("-60:80").unwrap(),
+            ..Default::default()
+        }
+    )
+    .is_err());
+}
@@ -1,30 +1,121 @@
 use chrono::{DateTime, Datelike, Local, Timelike};
 use std::error::Error;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
+pub mod braille;
+mod constraints;
+pub use constraints::{Adjustment, Bound, Constraints};
+mod diff;
+pub use diff::{ModelDiff, NodeDelta};
+mod firmware;
+pub use firmware::{read_profile, Firmware, Switching};
+mod fleet;
+pub use fleet::{Fleet, NodeStats, Outlier};
 mod frame;
-use frame::DataFrame;
+pub use frame::{
+    CalcOptions, DataFrame, DataRow, Direction, Estimator, Prior, PriorInfluence, ResidualStats,
+    Segment,
+};
+mod names;
+pub use names::NameTemplates;
+mod overrides;
+pub use overrides::Override;
+mod plan;
+pub use plan::{Plan, PlanOptions, Reason, Setpoint};
 mod plotter;
+pub use plotter::{parse_resolution, Format, PlotOptions};
+pub(crate) use plotter::{plot_overlay, Curve, Overlay};
+mod replay;
+pub use replay::{ErrorStats, Replay, ReplayPoint};
+mod update;
+pub use update::Update;
 
 #[derive(Default, Debug)]
 pub struct ThermoModel {
     raw_data: DataFrame,
     calc_data: DataFrame,
-    source_path: String,
+    /// влияние априорной модели на узлы `calc_data`
+    prior_influence: Vec<PriorInfluence>,
+    /// узлы, изменённые ограничениями таблицы
+    adjustments: Vec<Adjustment>,
+    /// ручные правки узлов и расчётные значения этих узлов
+    overrides: Vec<(Override, (i32, i32))>,
+    /// замечания о проверке модели, например после ручного просмотра
+    notes: Vec<String>,
+    source_path: PathBuf,
     serial_number: String,
-    date: DateTime<Local>,
+    date: Option<DateTime<Local>>,
+    plot_path: Option<PathBuf>,
+    plot_options: PlotOptions,
+    out_dir: Option<PathBuf>,
+    ct_dir: Option<PathBuf>,
+    no_open: bool,
+    names: NameTemplates,
 }
 
-fn abs_path(path: &str, suffix: &str) -> Result<String, Box<dyn Error>> {
-    let abs_path = std::env::current_dir()?.to_str().unwrap().to_owned() + "/" + path;
+/// Выходные файлы расчёта
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Artifact {
+    Plot,
+    Md,
+    AutoModel,
+    Ct,
+}
+
+impl std::str::FromStr for Artifact {
+    type Err = Box<dyn Error>;
 
-    let chunks: Vec<&str> = abs_path.split('.').collect();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plot" => Ok(Artifact::Plot),
+            "md" => Ok(Artifact::Md),
+            "auto_model" => Ok(Artifact::AutoModel),
+            "ct" => Ok(Artifact::Ct),
+            _ => Err(format!("Unknown output `{s}`"))?,
+        }
+    }
+}
 
-    let mut new_path = "".to_string();
-    for chunk in chunks.iter().take(chunks.len() - 1) {
-        new_path += chunk;
+/// есть ли графический дисплей для открытия графика
+pub fn display_available() -> bool {
+    if cfg!(any(windows, target_os = "macos")) {
+        return true;
     }
 
-    Ok(new_path + suffix)
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|var| std::env::var_os(var).map_or(false, |val| !val.is_empty()))
+}
+
+/// пары (температура, значение) для графика
+fn points(frame: &DataFrame, value: fn(&DataRow) -> Option<i32>) -> Vec<(i32, i32)> {
+    frame
+        .rows
+        .iter()
+        .filter_map(|row| Some((row.temp?, value(row)?)))
+        .collect()
+}
+
+/// сырые данные по сегментам для раскраски графика
+fn segment_points(
+    frame: &DataFrame,
+    value: fn(&DataRow) -> Option<i32>,
+) -> Vec<plotter::SegmentPoints> {
+    frame
+        .segments
+        .iter()
+        .map(|segment| plotter::SegmentPoints {
+            label: segment.comment.clone(),
+            direction: segment.direction(),
+            points: segment
+                .rows
+                .iter()
+                .filter_map(|row| Some((row.temp?, value(row)?)))
+                .collect(),
+        })
+        .collect()
 }
 
 impl std::fmt::Display for ThermoModel {
@@ -36,7 +127,7 @@ impl std::fmt::Display for ThermoModel {
         write!(f, "\r\n")?;
 
         write!(f, "|:-----|")?;
-        for (_, _) in self.calc_data.rows.iter().enumerate() {
+        for _ in self.calc_data.rows.iter() {
             write!(f, "----:|")?;
         }
         write!(f, "\r\n")?;
@@ -59,115 +150,629 @@ impl std::fmt::Display for ThermoModel {
         }
         write!(f, "\r\n")?;
 
+        if self
+            .prior_influence
+            .iter()
+            .any(|influence| *influence != PriorInfluence::None)
+        {
+            write!(f, "| prior|")?;
+            for influence in self.prior_influence.iter() {
+                let mark = match influence {
+                    PriorInfluence::None => "",
+                    PriorInfluence::Shrunk => "*",
+                    PriorInfluence::Filled => "**",
+                };
+                write!(f, " {:>3} |", mark)?;
+            }
+            write!(f, "\r\n\r\n")?;
+            write!(
+                f,
+                "prior: * shrunk toward the prior model, ** no data, taken from the prior model\r\n"
+            )?;
+        }
+
+        if !self.adjustments.is_empty() {
+            write!(f, "| limit|")?;
+            for row in self.calc_data.rows.iter() {
+                let limited = self
+                    .adjustments
+                    .iter()
+                    .any(|item| row.temp == Some(item.temp));
+                write!(f, " {:>3} |", if limited { "~" } else { "" })?;
+            }
+            write!(f, "\r\n\r\n")?;
+            for item in self.adjustments.iter() {
+                write!(f, "constraint {item}\r\n")?;
+            }
+        }
+
+        if !self.overrides.is_empty() {
+            write!(f, "| edit |")?;
+            for row in self.calc_data.rows.iter() {
+                let edited = self
+                    .overrides
+                    .iter()
+                    .any(|(item, _)| row.temp == Some(item.temp));
+                write!(f, " {:>3} |", if edited { "!" } else { "" })?;
+            }
+            write!(f, "\r\n\r\n")?;
+            for (item, calculated) in self.overrides.iter() {
+                write!(f, "override {}\r\n", item.describe(*calculated))?;
+            }
+        }
+        if !self.notes.is_empty() {
+            write!(f, "\r\n")?;
+            for note in self.notes.iter() {
+                write!(f, "note {note}\r\n")?;
+            }
+        }
         Ok(())
     }
 }
 
 impl ThermoModel {
-    pub fn from_path(
-        path: &str,
+    /// расчёт модели по сырым данным
+    pub fn fit(raw_data: DataFrame, options: &CalcOptions) -> Self {
+        let (calc_data, _, adjustments) = raw_data.calc_nodes(options, None);
+        ThermoModel {
+            raw_data,
+            calc_data,
+            adjustments,
+            ..Default::default()
+        }
+    }
+
+    /// расчёт модели со смещением узлов с малым числом точек к априорной модели
+    pub fn fit_with_prior(raw_data: DataFrame, options: &CalcOptions, prior: &Prior) -> Self {
+        let (calc_data, prior_influence, adjustments) = raw_data.calc_nodes(options, Some(prior));
+        ThermoModel {
+            raw_data,
+            calc_data,
+            prior_influence,
+            adjustments,
+            ..Default::default()
+        }
+    }
+
+    /// влияние априорной модели на узлы, пусто для модели без неё
+    pub fn prior_influence(&self) -> &[PriorInfluence] {
+        &self.prior_influence
+    }
+
+    /// узлы, изменённые ограничениями таблицы, пусто без ограничений
+    pub fn adjustments(&self) -> &[Adjustment] {
+        &self.adjustments
+    }
+
+    /// узлы готовой модели, изменённые ограничениями, например при дополнении данных
+    pub fn with_adjustments(&mut self, adjustments: Vec<Adjustment>) {
+        self.adjustments = adjustments;
+    }
+
+    /// ручные правки узлов поверх расчёта, отмечаются на графике, в md и `ct` файле
+    pub fn with_overrides(&mut self, overrides: &[Override]) -> Result<(), Box<dyn Error>> {
+        for item in overrides {
+            let calculated = item.apply(&mut self.calc_data)?;
+            self.overrides.push((item.clone(), calculated));
+        }
+        Ok(())
+    }
+
+    /// замечание о проверке модели, выводится в md и `ct` файл
+    pub fn with_note(&mut self, note: &str) {
+        self.notes.push(note.to_string());
+    }
+
+    /// ручные правки узлов и расчётные значения этих узлов
+    pub fn overrides(&self) -> &[(Override, (i32, i32))] {
+        &self.overrides
+    }
+
+    /// сырые данные с готовой моделью, например для валидации
+    pub fn new(raw_data: DataFrame, calc_data: DataFrame) -> Self {
+        ThermoModel {
+            raw_data,
+            calc_data,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(
+        path: P,
         recalc: bool,
-        optional_path: Option<&str>,
+        optional_path: Option<&Path>,
+        options: &CalcOptions,
     ) -> Result<Self, Box<dyn Error>> {
-        let mut item = ThermoModel {
-            raw_data: DataFrame::from_path(path)?,
-            source_path: path.to_string(),
-            date: Local::now(),
-            ..Default::default()
-        };
+        let path = path.as_ref();
+        let raw_data = DataFrame::from_path(path)?;
 
-        if recalc {
-            item.calc_data = item.raw_data.calc();
-            item.save_auto_model(&abs_path(path, "_auto_model.txt")?)?;
+        let mut item = if recalc {
+            Self::fit(raw_data, options)
         } else {
-            item.calc_data = match optional_path {
+            let calc_data = match optional_path {
                 Some(optional_path) => DataFrame::from_path(optional_path)?,
-                None => DataFrame::from_path(&abs_path(path, "_auto_model.txt")?)?,
-            }
+                None => DataFrame::from_path(Self::default_model_path(
+                    path,
+                    None,
+                    &NameTemplates::default(),
+                )?)?,
+            };
+            Self::new(raw_data, calc_data)
         };
+        item.with_source_path(path);
 
         Ok(item)
     }
 
+    /// модель без сырых данных, например для экспорта готовой таблицы
+    pub fn from_model_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let mut item = Self::new(DataFrame::default(), DataFrame::from_path(&path)?);
+        item.with_source_path(path);
+        Ok(item)
+    }
+
+    pub fn raw_data(&self) -> &DataFrame {
+        &self.raw_data
+    }
+
+    pub fn calc_data(&self) -> &DataFrame {
+        &self.calc_data
+    }
+
+    /// поправка модели при температуре `temp`
+    pub fn eval(&self, temp: i32) -> Option<(i32, i32)> {
+        self.calc_data.eval(temp)
+    }
+
+    /// отклонения сырых данных от модели
+    pub fn residuals(&self) -> DataFrame {
+        self.raw_data
+            .residuals_with(&self.calc_data, self.plot_options.switching)
+    }
+
+    /// статистика отклонений по осям x и y
+    pub fn residual_stats(&self, tolerance: i32) -> (ResidualStats, ResidualStats) {
+        let residuals = self.residuals();
+        let values = |value: fn(&DataRow) -> Option<i32>| -> Vec<i32> {
+            residuals.rows.iter().filter_map(value).collect()
+        };
+
+        (
+            ResidualStats::new(&values(|row| row.x), tolerance),
+            ResidualStats::new(&values(|row| row.y), tolerance),
+        )
+    }
+
+    /// сохранение выбранных выходных файлов
+    pub fn save(&self, outputs: &[Artifact]) -> Result<(), Box<dyn Error>> {
+        for artifact in [
+            Artifact::AutoModel,
+            Artifact::Ct,
+            Artifact::Plot,
+            Artifact::Md,
+        ] {
+            if !outputs.contains(&artifact) {
+                continue;
+            }
+            match artifact {
+                Artifact::AutoModel => self.auto_model()?,
+                Artifact::Ct => self.ct()?,
+                Artifact::Plot => self.plot()?,
+                Artifact::Md => self.md()?,
+            }
+        }
+        Ok(())
+    }
+
+    /// путь к выходному файлу с учётом папок, шаблонов имён и пути к графику
+    pub fn artifact_path(&self, artifact: Artifact) -> Result<PathBuf, Box<dyn Error>> {
+        match (&self.plot_path, artifact) {
+            (Some(path), Artifact::Plot) => Ok(path.to_owned()),
+            _ => self.output_path(artifact),
+        }
+    }
+
     pub fn plot(&self) -> Result<(), Box<dyn Error>> {
-        let img_path = abs_path(&self.source_path, "_with_model.png")?;
-        let header = format!(
-            "{} ({}.{}.{})",
-            self.serial_number,
-            self.date.day(),
-            self.date.month(),
-            self.date.year()
-        );
+        let img_path = self.artifact_path(Artifact::Plot)?;
 
+        let (panel_x, panel_y) = self.panels();
         plotter::plot(
             &img_path,
-            &header,
-            self.raw_data
-                .rows
-                .iter()
-                .map(|row| (row.temp.unwrap(), row.x.unwrap()))
-                .collect::<Vec<(i32, i32)>>(),
-            self.calc_data
-                .rows
-                .iter()
-                .map(|row| (row.temp.unwrap(), row.x.unwrap()))
-                .collect::<Vec<(i32, i32)>>(),
-            self.raw_data
-                .rows
-                .iter()
-                .map(|row| (row.temp.unwrap(), row.y.unwrap()))
-                .collect::<Vec<(i32, i32)>>(),
-            self.calc_data
-                .rows
-                .iter()
-                .map(|row| (row.temp.unwrap(), row.y.unwrap()))
-                .collect::<Vec<(i32, i32)>>(),
+            &self.plot_options,
+            &self.header(),
+            panel_x,
+            panel_y,
         )?;
-        opener::open(&img_path)?;
+
+        if !self.no_open && display_available() {
+            opener::open(&img_path)?;
+        }
         Ok(())
     }
 
+    /// запись графика текстом из символов Брайля шириной `columns` символов
+    /// для терминала без графического дисплея, например по SSH
+    pub fn write_text<W: Write>(
+        &self,
+        mut writer: W,
+        columns: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        let (panel_x, panel_y) = self.panels();
+        let rows = braille::panel_rows(columns);
+
+        let header = self.header();
+        let header = header.trim();
+        if !header.is_empty() {
+            writeln!(writer, "{header}")?;
+        }
+        let names = &self.plot_options.axis_names;
+        for (name, panel) in [(&names.0, panel_x), (&names.1, panel_y)] {
+            writeln!(writer, "{name}")?;
+            let switching = self.plot_options.switching;
+            for line in braille::chart(&panel.raw, &panel.calc, columns, rows, None, switching) {
+                writeln!(writer, "{line}")?;
+            }
+            if let Some(residuals) = panel.residuals {
+                // отклонения от модели строятся относительно нулевой модели на тех же узлах
+                let zero: Vec<(i32, i32)> = panel.calc.iter().map(|(temp, _)| (*temp, 0)).collect();
+                writeln!(writer, "{name}, отклонения от модели")?;
+                for line in braille::chart(&residuals, &zero, columns, rows / 2, None, switching) {
+                    writeln!(writer, "{line}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// запись графика в формате svg
+    pub fn write_svg<W: Write>(&self, mut writer: W) -> Result<(), Box<dyn Error>> {
+        let (panel_x, panel_y) = self.panels();
+        let svg = plotter::plot_svg(&self.plot_options, &self.header(), panel_x, panel_y)?;
+        writer.write_all(svg.as_bytes())?;
+        Ok(())
+    }
+
+    /// запись таблицы модели в формате markdown
+    pub fn write_md<W: Write>(&self, mut writer: W) -> Result<(), Box<dyn Error>> {
+        writer.write_all(self.to_string().as_bytes())?;
+        Ok(())
+    }
+
+    /// запись таблицы модели в формате auto_model и ct файлов
+    pub fn write_model<W: Write>(&self, writer: W) -> Result<(), Box<dyn Error>> {
+        self.calc_data.write_to(writer)
+    }
+
     pub fn md(&self) -> Result<(), Box<dyn Error>> {
-        std::fs::write(
-            &abs_path(&self.source_path, "_model.md")?,
-            self.to_string().as_bytes(),
-        )?;
+        std::fs::write(self.output_path(Artifact::Md)?, self.to_string().as_bytes())?;
         Ok(())
     }
 
+    /// сохранение вычисленной модели рядом с исходными данными
+    pub fn auto_model(&self) -> Result<(), Box<dyn Error>> {
+        self.save_auto_model(&self.output_path(Artifact::AutoModel)?)
+    }
+
+    /// сохранение `ct` файла, ручные правки дописываются строками-комментариями
     pub fn ct(&self) -> Result<(), Box<dyn Error>> {
-        let f_name = format!(
-            "tpk-k_{}_{}-{}-{}_{}-{}.ct",
-            self.serial_number,
-            self.date.year(),
-            self.date.month(),
-            self.date.day(),
-            self.date.hour(),
-            self.date.minute()
-        );
-        let path = std::env::current_dir()?.to_str().unwrap().to_owned() + "/" + &f_name;
+        let mut table = self.calc_data.to_string();
+        for item in self.adjustments.iter() {
+            table += &format!("\r\n# constraint {};;", item.to_string().replace(';', ","));
+        }
+        for (item, calculated) in self.overrides.iter() {
+            let note = item.describe(*calculated).replace(';', ",");
+            table += &format!("\r\n# override {note};;");
+        }
+        for note in self.notes.iter() {
+            table += &format!("\r\n# note {};;", note.replace(';', ","));
+        }
+        std::fs::write(self.output_path(Artifact::Ct)?, table.as_bytes())?;
+        Ok(())
+    }
 
-        self.save_auto_model(&path)
+    /// путь к исходным данным, от него строятся имена выходных файлов
+    pub fn with_source_path<P: AsRef<Path>>(&mut self, path: P) {
+        self.source_path = path.as_ref().to_path_buf();
+    }
+
+    /// дата расчёта для заголовка графика и имени `ct` файла
+    pub fn with_date(&mut self, date: DateTime<Local>) {
+        self.date = Some(date);
+    }
+
+    /// папка для `ct` файла, по умолчанию он сохраняется рядом с исходными данными
+    pub fn with_ct_dir<P: AsRef<Path>>(&mut self, dir: P) {
+        self.ct_dir = Some(dir.as_ref().to_path_buf());
     }
 
     pub fn with_serial_number(&mut self, serial_number: &str) {
         self.serial_number = serial_number.to_string();
     }
+
+    /// путь к файлу с графиком, формат определяется по расширению
+    pub fn with_plot_path<P: AsRef<Path>>(&mut self, path: P) {
+        self.plot_path = Some(path.as_ref().to_path_buf());
+    }
+
+    pub fn with_plot_options(&mut self, options: PlotOptions) {
+        self.plot_options = options;
+    }
+
+    /// папка для выходных файлов вместо папки с исходными данными
+    pub fn with_out_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(&dir)?;
+        self.out_dir = Some(dir.as_ref().to_path_buf());
+        Ok(())
+    }
+
+    /// не открывать график после построения
+    pub fn with_no_open(&mut self, no_open: bool) {
+        self.no_open = no_open;
+    }
+
+    /// шаблоны имён выходных файлов
+    pub fn with_names(&mut self, names: NameTemplates) {
+        self.names = names;
+    }
+
+    /// путь к модели по умолчанию для исходных данных `path`
+    pub fn default_model_path(
+        path: &Path,
+        out_dir: Option<&Path>,
+        names: &NameTemplates,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        let name = names::render(&names.auto_model, &[("stem", &names::stem(path)?)]);
+        Ok(match out_dir {
+            Some(dir) => dir.join(name),
+            None => names::parent(path).join(name),
+        })
+    }
+
+    /// путь к объединённым сырым данным по умолчанию для исходных данных `path`
+    pub fn default_merged_path(
+        path: &Path,
+        out_dir: Option<&Path>,
+        names: &NameTemplates,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        let name = names::render(&names.merged, &[("stem", &names::stem(path)?)]);
+        Ok(match out_dir {
+            Some(dir) => dir.join(name),
+            None => names::parent(path).join(name),
+        })
+    }
 }
 
 impl ThermoModel {
-    fn save_auto_model(&self, path: &str) -> Result<(), Box<dyn Error>> {
+    fn header(&self) -> String {
+        match self.date {
+            Some(date) => format!(
+                "{} ({}.{}.{})",
+                self.serial_number,
+                date.day(),
+                date.month(),
+                date.year()
+            ),
+            None => self.serial_number.clone(),
+        }
+    }
+
+    fn panels(&self) -> (plotter::Panel, plotter::Panel) {
+        let residuals = self.plot_options.residuals.then(|| self.residuals());
+
+        (
+            plotter::Panel {
+                raw: points(&self.raw_data, |row| row.x),
+                calc: points(&self.calc_data, |row| row.x),
+                residuals: residuals.as_ref().map(|r| points(r, |row| row.x)),
+                segments: segment_points(&self.raw_data, |row| row.x),
+                overrides: self.override_points('x'),
+            },
+            plotter::Panel {
+                raw: points(&self.raw_data, |row| row.y),
+                calc: points(&self.calc_data, |row| row.y),
+                residuals: residuals.as_ref().map(|r| points(r, |row| row.y)),
+                segments: segment_points(&self.raw_data, |row| row.y),
+                overrides: self.override_points('y'),
+            },
+        )
+    }
+
+    /// исправленные вручную узлы по оси `x` или `y` с подписью правки
+    fn override_points(&self, axis: char) -> Vec<(String, (i32, i32))> {
+        self.overrides
+            .iter()
+            .filter_map(|(item, calculated)| {
+                let value = match axis {
+                    'x' => item.x?,
+                    _ => item.y?,
+                };
+                Some((item.describe(*calculated), (item.temp, value)))
+            })
+            .collect()
+    }
+
+    /// путь к выходному файлу: в папке `out_dir`, если она задана, иначе рядом
+    /// с исходными данными, а `ct` файл в папке `ct_dir`, если она задана
+    fn output_path(&self, artifact: Artifact) -> Result<PathBuf, Box<dyn Error>> {
+        let template = self.names.get(artifact);
+        let date = match self.date {
+            Some(date) => format!(
+                "{}-{}-{}_{}-{}",
+                date.year(),
+                date.month(),
+                date.day(),
+                date.hour(),
+                date.minute()
+            ),
+            None if template.contains("{date}") => {
+                Err("Calculation date is not set, but it is used in the file name")?
+            }
+            None => String::default(),
+        };
+        let ext = self.plot_options.format_for(Path::new("")).to_string();
+        let name = names::render(
+            template,
+            &[
+                ("stem", &names::stem(&self.source_path)?),
+                ("serial", OsStr::new(&self.serial_number)),
+                ("date", OsStr::new(&date)),
+                ("ext", OsStr::new(&ext)),
+            ],
+        );
+
+        Ok(match (&self.out_dir, &self.ct_dir, artifact) {
+            (Some(dir), _, _) => dir.join(name),
+            (None, Some(dir), Artifact::Ct) => dir.join(name),
+            (None, _, _) => names::parent(&self.source_path).join(name),
+        })
+    }
+
+    fn save_auto_model(&self, path: &Path) -> Result<(), Box<dyn Error>> {
         self.calc_data.save_file(path)
     }
 }
 
 #[test]
 fn full_test() {
-    let model = ThermoModel::from_path("test/test_data.csv", true, None).unwrap();
+    let model =
+        ThermoModel::from_path("test/test_data.csv", true, None, &CalcOptions::default()).unwrap();
+    model.auto_model().unwrap();
     model.md().unwrap();
     model.plot().unwrap();
 
-    let model = ThermoModel::from_path("test/old_data.txt", true, None).unwrap();
+    let model =
+        ThermoModel::from_path("test/old_data.txt", true, None, &CalcOptions::default()).unwrap();
+    model.auto_model().unwrap();
     model.md().unwrap();
     model.plot().unwrap();
 }
+
+#[test]
+fn in_memory_api() {
+    let raw = DataFrame::from_reader(std::fs::File::open("test/test_data.csv").unwrap()).unwrap();
+    let model = ThermoModel::fit(raw, &CalcOptions::default());
+    assert_eq!(model.eval(-50), Some((2, -4)));
+    assert_eq!(model.eval(70), Some((-3, 0)));
+
+    let mut table = vec![];
+    model.write_model(&mut table).unwrap();
+    let expected = std::fs::read_to_string("test/test_data_auto_model.txt").unwrap();
+    assert_eq!(
+        String::from_utf8(table)
+            .unwrap()
+            .lines()
+            .collect::<Vec<_>>(),
+        expected.lines().collect::<Vec<_>>()
+    );
+
+    let (x, y) = model.residual_stats(1);
+    assert_eq!((x.count, y.count), (60, 60));
+    assert_eq!((x.max_abs, y.max_abs), (2, 1));
+
+    let mut svg = vec![];
+    model.write_svg(&mut svg).unwrap();
+    assert!(String::from_utf8(svg).unwrap().starts_with("<svg"));
+
+    let mut model = model;
+    model.with_source_path("data.csv");
+    assert!(model.output_path(Artifact::Ct).is_err());
+    model.with_serial_number("2БЛ1");
+    model.with_date(chrono::TimeZone::with_ymd_and_hms(&Local, 2024, 3, 5, 14, 7, 0).unwrap());
+    assert_eq!(
+        model.output_path(Artifact::Ct).unwrap(),
+        Path::new(".").join("tpk-k_2БЛ1_2024-3-5_14-7.ct")
+    );
+}
+
+#[test]
+fn empty_model() {
+    let model = ThermoModel::fit(DataFrame::default(), &CalcOptions::default());
+    assert!(model.calc_data().rows.is_empty());
+    assert_eq!(model.eval(0), None);
+}
+
+#[test]
+fn model_text() {
+    let raw = DataFrame::from_path("test/test_data.csv").unwrap();
+    let model = ThermoModel::fit(raw, &CalcOptions::default());
+    let mut text = vec![];
+    model.write_text(&mut text, 80).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    assert_eq!(text.lines().count(), 2 * (1 + 13 + 1));
+    assert!(text.lines().all(|line| line.chars().count() <= 80));
+}
+
+#[test]
+fn model_with_prior() {
+    let raw = DataFrame::from_path("test/test_data.csv").unwrap();
+    assert!(!ThermoModel::fit(raw.clone(), &CalcOptions::default())
+        .to_string()
+        .contains("prior"));
+    let prior = Prior {
+        model: "temp;dx;dy\r\n-50;10;-4\r\n70;-3;0".parse().unwrap(),
+        weight: 2,
+    };
+    let model = ThermoModel::fit_with_prior(raw, &CalcOptions::default(), &prior);
+    assert_eq!(model.prior_influence()[0], PriorInfluence::Shrunk);
+    assert_eq!(model.prior_influence()[20], PriorInfluence::None);
+    assert!(model.to_string().contains("| prior|   * |"));
+}
+
+#[test]
+fn model_with_constraints() {
+    let raw = DataFrame::from_path("test/test_data.csv").unwrap();
+    assert!(ThermoModel::fit(raw.clone(), &CalcOptions::default())
+        .adjustments()
+        .is_empty());
+    let constraints = Constraints {
+        max: (Some(1), None),
+        ..Default::default()
+    };
+    let model = ThermoModel::fit(
+        raw,
+        &CalcOptions {
+            constraints,
+            ..Default::default()
+        },
+    );
+    assert_eq!(model.eval(-50), Some((1, -4)));
+    assert_eq!(model.adjustments()[0].to_string(), "-50, dx 2 → 1: max");
+    assert!(model.to_string().contains("| limit|   ~ |"));
+    assert!(model
+        .to_string()
+        .contains("constraint -50, dx 2 → 1: max\r\n"));
+}
+
+#[test]
+fn model_with_overrides() {
+    let raw = DataFrame::from_path("test/test_data.csv").unwrap();
+    let mut model = ThermoModel::fit(raw, &CalcOptions::default());
+    model
+        .with_overrides(&[Override::parse("-50=3,", "Иванов", "ступенька").unwrap()])
+        .unwrap();
+    assert_eq!(model.eval(-50), Some((3, -4)));
+    assert!(model.to_string().contains("| edit |   ! |"));
+    assert!(model
+        .to_string()
+        .contains("override -50, dx 2 → 3 by Иванов: ступенька"));
+    model.with_note("review by Иванов; 1 point excluded");
+    assert!(model
+        .to_string()
+        .ends_with("note review by Иванов; 1 point excluded\r\n"));
+
+    let dir = std::env::temp_dir().join(format!("r-approx-model-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    model.with_source_path(dir.join("data.csv"));
+    model.with_serial_number("2БЛ1");
+    model.with_date(Local::now());
+    model.with_ct_dir(&dir);
+    model.ct().unwrap();
+    let ct = std::fs::read_to_string(model.output_path(Artifact::Ct).unwrap()).unwrap();
+    assert!(ct.contains("# override -50, dx 2 → 3 by Иванов: ступенька;;"));
+    assert!(ct.ends_with("# note review by Иванов, 1 point excluded;;"));
+    assert_eq!(
+        ct.parse::<DataFrame>().unwrap().rows,
+        model.calc_data().rows
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
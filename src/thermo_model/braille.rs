@@ -0,0 +1,201 @@
+use std::cmp::{max, min};
+
+use super::Switching;
+
+/// биты точек символа Брайля по столбцу и строке
+const DOTS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+/// ширина подписей оси y
+const LABEL_WIDTH: usize = 5;
+
+/// Поле из символов Брайля, в каждом символе 2×4 точки
+#[derive(Debug, Clone, PartialEq)]
+pub struct Canvas {
+    columns: usize,
+    rows: usize,
+    x: (f64, f64),
+    y: (f64, f64),
+    cells: Vec<u8>,
+}
+
+impl Canvas {
+    /// поле `columns`×`rows` символов для диапазонов значений `x` и `y`
+    pub fn new(columns: usize, rows: usize, x: (f64, f64), y: (f64, f64)) -> Self {
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        Self {
+            columns,
+            rows,
+            x,
+            y,
+            cells: vec![0; columns * rows],
+        }
+    }
+
+    /// положение значения в точках поля, строки отсчитываются сверху
+    fn position(&self, x: f64, y: f64) -> (f64, f64) {
+        let (width, height) = (2 * self.columns - 1, 4 * self.rows - 1);
+        (
+            (x - self.x.0) / (self.x.1 - self.x.0) * width as f64,
+            (self.y.1 - y) / (self.y.1 - self.y.0) * height as f64,
+        )
+    }
+
+    /// точка поля, ближайшая к значению, или `None` вне диапазонов
+    fn dot(&self, x: f64, y: f64) -> Option<(usize, usize)> {
+        let (col, row) = self.position(x, y);
+        let (col, row) = (col.round(), row.round());
+        (col >= 0.0
+            && row >= 0.0
+            && col < (2 * self.columns) as f64
+            && row < (4 * self.rows) as f64)
+            .then_some((col as usize, row as usize))
+    }
+
+    pub fn point(&mut self, x: f64, y: f64) {
+        if let Some((col, row)) = self.dot(x, y) {
+            self.cells[row / 4 * self.columns + col / 2] |= DOTS[col % 2][row % 4];
+        }
+    }
+
+    /// отрезок по точкам поля, `step` — шаг между точками, 2 для пунктира
+    pub fn line(&mut self, from: (f64, f64), to: (f64, f64), step: usize) {
+        let (a, b) = (self.position(from.0, from.1), self.position(to.0, to.1));
+        let count = ((a.0 - b.0).abs().max((a.1 - b.1).abs()).ceil() as usize).max(1);
+        for i in (0..=count).step_by(step.max(1)) {
+            let t = i as f64 / count as f64;
+            self.point(from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+        }
+    }
+
+    /// строки символов поля
+    pub fn lines(&self) -> Vec<String> {
+        self.cells
+            .chunks(self.columns)
+            .map(|row| {
+                row.iter()
+                    .map(|bits| char::from_u32(0x2800 + *bits as u32).unwrap_or(' '))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// высота графика одной оси в строках для ширины `columns` символов,
+/// чтобы пропорции были близки к графику в файле
+pub fn panel_rows(columns: usize) -> usize {
+    (columns / 6).clamp(6, 20)
+}
+
+/// текстовый график одной оси шириной `columns` и высотой `rows` символов:
+/// сырые точки, ступени модели с переключением по правилу `switching`, нулевая линия
+/// и вертикальный курсор на температуре `cursor`
+pub fn chart(
+    raw: &[(i32, i32)],
+    calc: &[(i32, i32)],
+    columns: usize,
+    rows: usize,
+    cursor: Option<i32>,
+    switching: Switching,
+) -> Vec<String> {
+    let step = match calc {
+        [first, second, ..] if second.0 > first.0 => second.0 - first.0,
+        _ => 1,
+    };
+    let temps = raw.iter().chain(calc.iter()).map(|(temp, _)| *temp);
+    let x = (
+        temps.clone().min().unwrap_or(0) - step / 2,
+        temps.max().unwrap_or(0) + step / 2,
+    );
+    let values = raw.iter().chain(calc.iter()).map(|(_, value)| *value);
+    let y = (
+        min(values.clone().min().unwrap_or(0), 0) - 1,
+        max(values.max().unwrap_or(0), 0) + 1,
+    );
+
+    let columns = columns.saturating_sub(LABEL_WIDTH).max(1);
+    let mut canvas = Canvas::new(
+        columns,
+        rows,
+        (x.0 as f64, x.1 as f64),
+        (y.0 as f64, y.1 as f64),
+    );
+    canvas.line((x.0 as f64, 0.0), (x.1 as f64, 0.0), 2);
+    if let Some(temp) = cursor {
+        canvas.line((temp as f64, y.0 as f64), (temp as f64, y.1 as f64), 2);
+    }
+    for (temp, value) in raw {
+        canvas.point(*temp as f64, *value as f64);
+    }
+    // как на графике в файле: при гистерезисе в зоне действуют обе ступени
+    for pair in calc.windows(2) {
+        let ((t0, v0), (t1, v1)) = (pair[0], pair[1]);
+        let (down, up) = switching.thresholds(t0, t1);
+        let (down, up) = (down as f64, up as f64);
+        canvas.line((t0 as f64, v0 as f64), (up, v0 as f64), 1);
+        canvas.line((down, v1 as f64), (t1 as f64, v1 as f64), 1);
+        if v0 != v1 {
+            for switch in [up, down] {
+                canvas.line((switch, v0 as f64), (switch, v1 as f64), 1);
+            }
+        }
+    }
+    if let [(temp, value)] = calc {
+        canvas.point(*temp as f64, *value as f64);
+    }
+
+    // номер строки поля, в которой лежит значение
+    let row_of =
+        |value: i32| ((y.1 - value) as f64 / (y.1 - y.0) as f64 * (rows - 1) as f64).round();
+    let mut lines: Vec<String> = canvas
+        .lines()
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let label = [y.1, 0, y.0]
+                .into_iter()
+                .find(|value| row_of(*value) == i as f64)
+                .map_or(String::default(), |value| value.to_string());
+            format!("{label:>width$}│{line}", width = LABEL_WIDTH - 1)
+        })
+        .collect();
+
+    let (first, last) = (x.0.to_string(), x.1.to_string());
+    lines.push(format!(
+        "{:width$}{first}{last:>rest$}",
+        "",
+        width = LABEL_WIDTH,
+        rest = columns.saturating_sub(first.chars().count())
+    ));
+    lines
+}
+
+#[test]
+fn braille_chart() {
+    let mut canvas = Canvas::new(2, 1, (0.0, 3.0), (0.0, 3.0));
+    canvas.point(0.0, 3.0);
+    canvas.point(3.0, 0.0);
+    assert_eq!(canvas.lines(), vec!["\u{2801}\u{2880}"]);
+    canvas.point(5.0, 0.0);
+    canvas.line((0.0, 0.0), (3.0, 0.0), 1);
+    assert_eq!(canvas.lines(), vec!["\u{28c1}\u{28c0}"]);
+
+    let lines = chart(
+        &[(0, 1), (6, 2)],
+        &[(0, 1), (6, 2)],
+        25,
+        4,
+        Some(3),
+        Switching::Midpoint,
+    );
+    assert_eq!(lines.len(), 5);
+    assert!(lines.iter().all(|line| line.chars().count() == 25));
+    assert!(lines[0].starts_with("   3│"));
+    assert!(lines[4].trim_start().starts_with("-3"));
+    // ступень переключается на следующем узле, а не посередине
+    let steps = |switching: Switching| chart(&[], &[(0, 0), (6, 3)], 25, 4, None, switching);
+    assert_ne!(steps(Switching::AtNode), steps(Switching::Midpoint));
+    assert_eq!(steps(Switching::Hysteresis(0)), steps(Switching::Midpoint));
+    assert_eq!(
+        (panel_rows(40), panel_rows(120), panel_rows(300)),
+        (6, 20, 20)
+    );
+}
@@ -0,0 +1,282 @@
+use std::error::Error;
+
+use super::{DataFrame, DataRow};
+
+/// Ограничения таблицы коррекции: диапазон изделия, скачки между узлами, монотонность
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Constraints {
+    /// наименьшие допустимые значения по осям x и y
+    pub min: (Option<i32>, Option<i32>),
+    /// наибольшие допустимые значения по осям x и y
+    pub max: (Option<i32>, Option<i32>),
+    /// наибольшая разность значений соседних узлов
+    pub max_step: Option<u32>,
+    /// значения по каждой оси не убывают или не возрастают с температурой,
+    /// направление выбирается ближайшее к расчёту
+    pub monotonic: bool,
+}
+
+/// Ограничение, действующее в изменённом узле
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Min,
+    Max,
+    Step,
+    Monotonic,
+}
+
+impl std::fmt::Display for Bound {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Bound::Min => write!(f, "min"),
+            Bound::Max => write!(f, "max"),
+            Bound::Step => write!(f, "step"),
+            Bound::Monotonic => write!(f, "monotonic"),
+        }
+    }
+}
+
+/// Значение узла, изменённое ограничениями
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adjustment {
+    pub temp: i32,
+    /// ось `x` или `y`
+    pub axis: char,
+    pub calculated: i32,
+    pub value: i32,
+    /// ограничения, на границе которых оказался узел
+    pub bounds: Vec<Bound>,
+}
+
+impl std::fmt::Display for Adjustment {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}, d{} {} → {}",
+            self.temp, self.axis, self.calculated, self.value
+        )?;
+        let bounds: Vec<String> = self.bounds.iter().map(|bound| bound.to_string()).collect();
+        if !bounds.is_empty() {
+            write!(f, ": {}", bounds.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Constraints {
+    /// нет ни одного ограничения
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        for (axis, min, max) in [('x', self.min.0, self.max.0), ('y', self.min.1, self.max.1)] {
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    Err(format!("Invalid range {min}..{max} of axis {axis}"))?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// замена значений модели `model` ближайшими в смысле наименьших квадратов,
+    /// удовлетворяющими ограничениям, возвращает изменённые узлы
+    pub fn apply(&self, model: &mut DataFrame) -> Vec<Adjustment> {
+        if self.is_empty() {
+            return vec![];
+        }
+        let mut adjustments = vec![];
+        let temps: Vec<i32> = model.rows.iter().filter_map(|row| row.temp).collect();
+        for (axis, min, max) in [('x', self.min.0, self.max.0), ('y', self.min.1, self.max.1)] {
+            let calculated: Vec<i32> = model
+                .rows
+                .iter_mut()
+                .filter(|row| row.temp.is_some())
+                .map(|row| axis_value(row, axis).unwrap_or(0))
+                .collect();
+            let fitted = self.fit(&calculated, (min, max));
+
+            for (i, row) in model
+                .rows
+                .iter_mut()
+                .filter(|row| row.temp.is_some())
+                .enumerate()
+            {
+                *axis_value(row, axis) = Some(fitted[i]);
+                if fitted[i] == calculated[i] {
+                    continue;
+                }
+                let neighbours = [i.checked_sub(1), Some(i + 1)]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|j| fitted.get(j).copied());
+                let mut bounds = vec![];
+                if min == Some(fitted[i]) {
+                    bounds.push(Bound::Min);
+                }
+                if max == Some(fitted[i]) {
+                    bounds.push(Bound::Max);
+                }
+                if let Some(step) = self.max_step {
+                    if neighbours
+                        .clone()
+                        .any(|other| (other - fitted[i]).unsigned_abs() == step)
+                    {
+                        bounds.push(Bound::Step);
+                    }
+                }
+                if self.monotonic && neighbours.clone().any(|other| other == fitted[i]) {
+                    bounds.push(Bound::Monotonic);
+                }
+                adjustments.push(Adjustment {
+                    temp: temps[i],
+                    axis,
+                    calculated: calculated[i],
+                    value: fitted[i],
+                    bounds,
+                });
+            }
+        }
+        adjustments.sort_by_key(|item| (item.temp, item.axis));
+        adjustments
+    }
+
+    /// ближайшие к `values` целые значения в пределах `range`, при монотонности
+    /// выбирается направление с меньшей суммой квадратов отклонений
+    fn fit(&self, values: &[i32], range: (Option<i32>, Option<i32>)) -> Vec<i32> {
+        if !self.monotonic {
+            return fit_axis(values, range, self.max_step, None).0;
+        }
+        let rising = values.last() >= values.first();
+        let (first, first_cost) = fit_axis(values, range, self.max_step, Some(rising));
+        let (second, second_cost) = fit_axis(values, range, self.max_step, Some(!rising));
+        if second_cost < first_cost {
+            second
+        } else {
+            first
+        }
+    }
+}
+
+/// значение строки по оси `x` или `y`
+fn axis_value(row: &mut DataRow, axis: char) -> &mut Option<i32> {
+    match axis {
+        'x' => &mut row.x,
+        _ => &mut row.y,
+    }
+}
+
+/// точное решение динамическим программированием по целым значениям: стоимость
+/// значения в узле — квадрат отклонения плюс наименьшая стоимость допустимого
+/// значения в предыдущем узле; `rising` задаёт направление монотонности
+fn fit_axis(
+    values: &[i32],
+    (min, max): (Option<i32>, Option<i32>),
+    step: Option<u32>,
+    rising: Option<bool>,
+) -> (Vec<i32>, i64) {
+    let (Some(low), Some(high)) = (values.iter().min(), values.iter().max()) else {
+        return (vec![], 0);
+    };
+    // решение не выходит за пределы расчётных значений, сжатых к допустимому диапазону
+    let clamp = |value: i32| {
+        let value = min.map_or(value, |min| value.max(min));
+        max.map_or(value, |max| value.min(max))
+    };
+    let (low, high) = (clamp(*low), clamp(*high));
+    let domain: Vec<i32> = (low..=high).collect();
+    let deviation = |i: usize, value: i32| (value as i64 - values[i] as i64).pow(2);
+    let allowed = |previous: i32, value: i32| {
+        let delta = value - previous;
+        step.map_or(true, |step| delta.unsigned_abs() <= step)
+            && match rising {
+                Some(true) => delta >= 0,
+                Some(false) => delta <= 0,
+                None => true,
+            }
+    };
+
+    let mut cost: Vec<i64> = domain.iter().map(|value| deviation(0, *value)).collect();
+    let mut choices: Vec<Vec<usize>> = vec![];
+    for i in 1..values.len() {
+        let mut next = vec![i64::MAX; domain.len()];
+        let mut choice = vec![0; domain.len()];
+        for (k, value) in domain.iter().enumerate() {
+            for (j, previous) in domain.iter().enumerate() {
+                if cost[j] < next[k] && allowed(*previous, *value) {
+                    (next[k], choice[k]) = (cost[j], j);
+                }
+            }
+            next[k] = next[k].saturating_add(deviation(i, *value));
+        }
+        cost = next;
+        choices.push(choice);
+    }
+
+    let (mut k, total) = cost
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, cost)| **cost)
+        .map(|(k, cost)| (k, *cost))
+        .unwrap_or_default();
+    let mut fitted = vec![domain[k]];
+    for choice in choices.iter().rev() {
+        k = choice[k];
+        fitted.push(domain[k]);
+    }
+    fitted.reverse();
+    (fitted, total)
+}
+
+#[test]
+fn table_constraints() {
+    let mut model: DataFrame = "temp;dx;dy\r\n-10;9;0\r\n0;2;1\r\n10;1;0\r\n20;0;5\r\n30;-1;6"
+        .parse()
+        .unwrap();
+    let calculated = model.clone();
+    assert!(Constraints::default().apply(&mut model).is_empty());
+    assert_eq!(model, calculated);
+
+    // ограничения, которым таблица уже удовлетворяет, её не меняют
+    let loose = Constraints {
+        min: (Some(-10), Some(-10)),
+        max: (Some(10), Some(10)),
+        max_step: Some(7),
+        ..Default::default()
+    };
+    assert!(loose.apply(&mut model).is_empty());
+
+    let constraints = Constraints {
+        max: (Some(5), None),
+        max_step: Some(3),
+        monotonic: true,
+        ..Default::default()
+    };
+    let adjustments = constraints.apply(&mut model);
+    let values = |model: &DataFrame, value: fn(&DataRow) -> Option<i32>| -> Vec<i32> {
+        model.rows.iter().filter_map(value).collect()
+    };
+    // x: предел 5 и шаг 3 при убывании, y: возрастание с наименьшими отклонениями
+    assert_eq!(values(&model, |row| row.x), [5, 2, 1, 0, -1]);
+    assert_eq!(values(&model, |row| row.y), [0, 1, 1, 4, 6]);
+    assert_eq!(
+        adjustments
+            .iter()
+            .map(|item| item.to_string())
+            .collect::<Vec<_>>(),
+        [
+            "-10, dx 9 → 5: max, step",
+            "10, dy 0 → 1: step, monotonic",
+            "20, dy 5 → 4: step",
+        ]
+    );
+
+    assert!(Constraints {
+        min: (None, Some(3)),
+        max: (None, Some(2)),
+        ..Default::default()
+    }
+    .check()
+    .is_err());
+}
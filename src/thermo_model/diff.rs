@@ -0,0 +1,220 @@
+use std::error::Error;
+use std::path::Path;
+
+use super::plotter::{self, Curve, Overlay, PlotOptions};
+use super::DataFrame;
+
+/// Значения двух моделей в узле сетки
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeDelta {
+    pub temp: i32,
+    pub old: (i32, i32),
+    pub new: (i32, i32),
+}
+
+impl NodeDelta {
+    /// изменение по осям x и y
+    pub fn delta(&self) -> (i32, i32) {
+        (self.new.0 - self.old.0, self.new.1 - self.old.1)
+    }
+
+    /// наибольшее по модулю изменение по обеим осям
+    pub fn max_change(&self) -> i32 {
+        let (dx, dy) = self.delta();
+        dx.abs().max(dy.abs())
+    }
+}
+
+/// Сравнение двух моделей в узлах обеих моделей
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelDiff {
+    pub nodes: Vec<NodeDelta>,
+    /// допустимое изменение узла, без порога изменения только выводятся
+    pub threshold: Option<i32>,
+}
+
+impl ModelDiff {
+    pub fn new(old: &DataFrame, new: &DataFrame, threshold: Option<i32>) -> Self {
+        let mut temps: Vec<i32> = old
+            .rows
+            .iter()
+            .chain(new.rows.iter())
+            .filter_map(|row| row.temp)
+            .collect();
+        temps.sort();
+        temps.dedup();
+
+        Self {
+            nodes: temps
+                .into_iter()
+                .filter_map(|temp| {
+                    Some(NodeDelta {
+                        temp,
+                        old: old.eval(temp)?,
+                        new: new.eval(temp)?,
+                    })
+                })
+                .collect(),
+            threshold,
+        }
+    }
+
+    /// наибольшее по модулю изменение узла
+    pub fn max_change(&self) -> i32 {
+        self.nodes
+            .iter()
+            .map(|node| node.max_change())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// превышает ли изменение допустимое
+    pub fn exceeds(&self) -> bool {
+        self.over(self.max_change())
+    }
+
+    /// превышает ли изменение `delta` допустимое
+    fn over(&self, delta: i32) -> bool {
+        self.threshold
+            .map_or(false, |threshold| delta.abs() > threshold)
+    }
+
+    /// раздел отчёта в формате markdown, `labels` — названия старой и новой модели
+    pub fn md(&self, labels: (&str, &str)) -> String {
+        let mut md = format!("## {} → {}\r\n\r\n", labels.0, labels.1);
+        md += "| temp | old dx | new dx | Δdx | old dy | new dy | Δdy |\r\n";
+        md += "|-----:|-------:|-------:|----:|-------:|-------:|----:|\r\n";
+        for node in self.nodes.iter() {
+            let (dx, dy) = node.delta();
+            let mark = |delta: i32| {
+                if self.over(delta) {
+                    format!("**{delta}**")
+                } else {
+                    delta.to_string()
+                }
+            };
+            md += &format!(
+                "| {} | {} | {} | {} | {} | {} | {} |\r\n",
+                node.temp,
+                node.old.0,
+                node.new.0,
+                mark(dx),
+                node.old.1,
+                node.new.1,
+                mark(dy)
+            );
+        }
+        md += &format!("\r\n{}\r\n", self.verdict());
+        md
+    }
+
+    /// наложенные кривые обеих моделей, `labels` — названия старой и новой модели
+    pub fn plot(
+        &self,
+        path: &Path,
+        options: &PlotOptions,
+        labels: (&str, &str),
+    ) -> Result<(), Box<dyn Error>> {
+        let overlay = |value: fn((i32, i32)) -> i32| Overlay {
+            curves: vec![
+                Curve {
+                    label: labels.0.to_string(),
+                    points: self
+                        .nodes
+                        .iter()
+                        .map(|node| (node.temp, value(node.old)))
+                        .collect(),
+                    highlighted: true,
+                },
+                Curve {
+                    label: labels.1.to_string(),
+                    points: self
+                        .nodes
+                        .iter()
+                        .map(|node| (node.temp, value(node.new)))
+                        .collect(),
+                    highlighted: true,
+                },
+            ],
+            ..Default::default()
+        };
+
+        plotter::plot_overlay(
+            path,
+            options,
+            &self.verdict(),
+            overlay(|(x, _)| x),
+            overlay(|(_, y)| y),
+        )
+    }
+
+    fn verdict(&self) -> String {
+        let max = self.max_change();
+        match self.threshold {
+            Some(threshold) if self.exceeds() => {
+                format!("max change: {max}, exceeds threshold {threshold}")
+            }
+            Some(threshold) => format!("max change: {max}, within threshold {threshold}"),
+            None => format!("max change: {max}"),
+        }
+    }
+}
+
+/// таблица изменений: узлы, изменения и итог
+impl std::fmt::Display for ModelDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "temp   old dx  new dx   Δdx   old dy  new dy   Δdy")?;
+        for node in self.nodes.iter() {
+            let (dx, dy) = node.delta();
+            writeln!(
+                f,
+                "{:4} {:8} {:7} {:5}{} {:7} {:7} {:5}{}",
+                node.temp,
+                node.old.0,
+                node.new.0,
+                dx,
+                if self.over(dx) { "!" } else { " " },
+                node.old.1,
+                node.new.1,
+                dy,
+                if self.over(dy) { "!" } else { " " },
+            )?;
+        }
+        write!(f, "{}", self.verdict())
+    }
+}
+
+#[test]
+fn model_diff() {
+    let old: DataFrame = "temp;dx;dy\r\n-2;1;-1\r\n4;2;-2\r\n10;3;-3"
+        .parse()
+        .unwrap();
+    let new: DataFrame = "temp;dx;dy\r\n-2;1;0\r\n4;5;-2\r\n7;5;-1".parse().unwrap();
+
+    let diff = ModelDiff::new(&old, &new, Some(1));
+    assert_eq!(
+        diff.nodes.iter().map(|node| node.temp).collect::<Vec<_>>(),
+        vec![-2, 4, 7, 10]
+    );
+    assert_eq!(diff.nodes[1].delta(), (3, 0));
+    assert_eq!(diff.nodes[2].delta(), (2, 2));
+    assert_eq!(diff.max_change(), 3);
+    assert!(diff.exceeds());
+
+    let md = diff.md(("old.ct", "new_auto_model.txt"));
+    assert!(md.starts_with("## old.ct → new_auto_model.txt"));
+    assert!(md.contains("| 4 | 2 | 5 | **3** | -2 | -2 | 0 |"));
+    assert!(diff
+        .to_string()
+        .ends_with("max change: 3, exceeds threshold 1"));
+
+    assert!(!ModelDiff::new(&old, &old, Some(0)).exceeds());
+
+    // без порога изменения только выводятся
+    let diff = ModelDiff::new(&old, &new, None);
+    assert!(!diff.exceeds());
+    assert!(diff
+        .md(("old", "new"))
+        .contains("| 4 | 2 | 5 | 3 | -2 | -2 | 0 |"));
+    assert!(diff.to_string().ends_with("max change: 3"));
+}
@@ -0,0 +1,202 @@
+use std::error::Error;
+use std::io::Read;
+
+use super::DataFrame;
+
+/// Правило переключения ступеней таблицы при изменении температуры
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Switching {
+    /// посередине между соседними узлами
+    #[default]
+    Midpoint,
+    /// при достижении следующего узла: значение узла действует до следующего узла
+    AtNode,
+    /// посередине с зоной нечувствительности `±°C`: при нагреве ступень переключается
+    /// выше середины, при охлаждении — ниже
+    Hysteresis(i32),
+}
+
+impl std::fmt::Display for Switching {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Switching::Midpoint => write!(f, "midpoint"),
+            Switching::AtNode => write!(f, "node"),
+            Switching::Hysteresis(band) => write!(f, "hysteresis:{band}"),
+        }
+    }
+}
+
+impl std::str::FromStr for Switching {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!("Unknown switching rule `{s}`, expected midpoint, node or hysteresis:DEGREES")
+        };
+        match s.split_once(':') {
+            None if s == "midpoint" => Ok(Switching::Midpoint),
+            None if s == "node" => Ok(Switching::AtNode),
+            Some(("hysteresis", band)) => match band.trim().parse() {
+                Ok(band) if band >= 0 => Ok(Switching::Hysteresis(band)),
+                _ => Err(invalid())?,
+            },
+            _ => Err(invalid())?,
+        }
+    }
+}
+
+impl Switching {
+    /// температуры переключения между узлами `low` и `high`: при охлаждении и при нагреве
+    pub fn thresholds(&self, low: i32, high: i32) -> (i32, i32) {
+        // середина в целых, как в расчёте прошивки
+        let middle = (low + high) / 2;
+        match self {
+            Switching::Midpoint => (middle, middle),
+            Switching::AtNode => (high, high),
+            Switching::Hysteresis(band) => (middle - band, middle + band),
+        }
+    }
+}
+
+/// Эмулятор поиска по таблице коррекции в изделии: текущая ступень зависит
+/// от правила переключения и, при гистерезисе, от предыдущей ступени
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Firmware {
+    nodes: Vec<(i32, (i32, i32))>,
+    switching: Switching,
+    /// номер текущей ступени, `None` до первого опроса
+    index: Option<usize>,
+}
+
+impl Firmware {
+    pub fn new(table: &DataFrame, switching: Switching) -> Result<Self, Box<dyn Error>> {
+        let nodes: Vec<_> = table
+            .rows
+            .iter()
+            .filter_map(|row| Some((row.temp?, (row.x?, row.y?))))
+            .collect();
+        if nodes.is_empty() {
+            Err("Empty table")?
+        }
+        Ok(Self {
+            nodes,
+            switching,
+            index: None,
+        })
+    }
+
+    /// возврат в состояние после включения
+    pub fn reset(&mut self) {
+        self.index = None;
+    }
+
+    /// коррекция при температуре `temp`
+    pub fn lookup(&mut self, temp: f64) -> (i32, i32) {
+        let thresholds = |index: usize| {
+            self.switching
+                .thresholds(self.nodes[index].0, self.nodes[index + 1].0)
+        };
+        let mut index = match self.index {
+            Some(index) => index,
+            // после включения ступень выбирается по температуре без учёта гистерезиса
+            None => {
+                let switching = match self.switching {
+                    Switching::Hysteresis(_) => Switching::Midpoint,
+                    switching => switching,
+                };
+                (0..self.nodes.len() - 1)
+                    .take_while(|i| {
+                        temp >= switching
+                            .thresholds(self.nodes[*i].0, self.nodes[*i + 1].0)
+                            .1 as f64
+                    })
+                    .count()
+            }
+        };
+        while index + 1 < self.nodes.len() && temp >= thresholds(index).1 as f64 {
+            index += 1;
+        }
+        while index > 0 && temp < thresholds(index - 1).0 as f64 {
+            index -= 1;
+        }
+        self.index = Some(index);
+        self.nodes[index].1
+    }
+
+    /// коррекция для каждой температуры ряда `temps` по порядку
+    pub fn replay(&mut self, temps: &[f64]) -> Vec<(i32, i32)> {
+        temps.iter().map(|temp| self.lookup(*temp)).collect()
+    }
+}
+
+/// температурный профиль `time;temp` из потока: время в минутах и температура, °C
+pub fn read_profile<R: Read>(mut reader: R) -> Result<Vec<(f64, f64)>, Box<dyn Error>> {
+    let mut table = String::new();
+    reader.read_to_string(&mut table)?;
+    let mut lines = table.lines();
+    if !lines
+        .next()
+        .unwrap_or_default()
+        .trim_end()
+        .starts_with("time;temp")
+    {
+        Err("Invalid profile header, expected `time;temp`")?
+    }
+
+    let mut profile = vec![];
+    for line in lines.filter(|line| !line.trim().is_empty()) {
+        let mut values = line.split(';').map(|value| value.trim().parse::<f64>());
+        match (values.next(), values.next()) {
+            (Some(Ok(time)), Some(Ok(temp))) => profile.push((time, temp)),
+            _ => Err(format!("Invalid profile row `{line}`"))?,
+        }
+    }
+    Ok(profile)
+}
+
+#[test]
+fn firmware_switching() {
+    let table: DataFrame = "temp;dx;dy\r\n0;0;0\r\n6;1;-1\r\n12;2;-2".parse().unwrap();
+    let values = |switching: Switching, temps: &[f64]| -> Vec<i32> {
+        let mut firmware = Firmware::new(&table, switching).unwrap();
+        firmware.replay(temps).iter().map(|(x, _)| *x).collect()
+    };
+
+    let rising = [-5.0, 2.9, 3.0, 5.9, 6.0, 9.0, 20.0];
+    assert_eq!(values(Switching::Midpoint, &rising), [0, 0, 1, 1, 1, 2, 2]);
+    assert_eq!(values(Switching::AtNode, &rising), [0, 0, 0, 0, 1, 1, 2]);
+    // при гистерезисе ступень держится до середины ±1 °C в обе стороны
+    let cycle = [2.0, 3.5, 4.0, 3.0, 2.0, 1.9];
+    assert_eq!(values(Switching::Hysteresis(1), &cycle), [0, 0, 1, 1, 1, 0]);
+    assert_eq!(values(Switching::Midpoint, &cycle), [0, 1, 1, 1, 0, 0]);
+
+    assert_eq!(table.eval(2), Some((0, 0)));
+    assert_eq!(table.eval(3), Some((1, -1)));
+    assert_eq!(table.eval(-3), Some((0, 0)));
+    assert_eq!(table.eval(15), Some((2, -2)));
+    assert!(Firmware::new(&DataFrame::default(), Switching::AtNode).is_err());
+
+    // при охлаждении с гистерезисом в точке 2 ещё действует верхняя ступень
+    let raw: DataFrame =
+        "temp;dx;dy\r\n# plus;;\r\n2;0;0\r\n4;1;-1\r\n# minus;;\r\n4;1;-1\r\n2;1;-1"
+            .parse()
+            .unwrap();
+    let worst = |residuals: DataFrame| residuals.rows.iter().map(|row| row.x.unwrap()).max();
+    assert_eq!(worst(raw.residuals(&table)), Some(1));
+    assert_eq!(
+        worst(raw.residuals_with(&table, Switching::Hysteresis(1))),
+        Some(0)
+    );
+
+    assert_eq!("node".parse::<Switching>().unwrap(), Switching::AtNode);
+    assert_eq!(
+        "hysteresis:2".parse::<Switching>().unwrap(),
+        Switching::Hysteresis(2)
+    );
+    assert_eq!(Switching::Hysteresis(2).to_string(), "hysteresis:2");
+    assert!("hysteresis".parse::<Switching>().is_err());
+
+    let profile = read_profile("time;temp\r\n0;20\r\n1.5;21.25\r\n".as_bytes()).unwrap();
+    assert_eq!(profile, vec![(0.0, 20.0), (1.5, 21.25)]);
+    assert!(read_profile("temp;dx;dy\r\n0;0;0".as_bytes()).is_err());
+}
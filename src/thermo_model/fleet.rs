@@ -0,0 +1,269 @@
+use std::error::Error;
+use std::path::Path;
+
+use super::plotter::{self, Curve, Overlay, PlotOptions};
+use super::{DataFrame, DataRow};
+
+/// Статистика поправок партии изделий в узле сетки
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeStats {
+    pub temp: i32,
+    /// среднее значение по осям x и y
+    pub mean: (f64, f64),
+    /// среднеквадратичное отклонение по осям x и y
+    pub std_dev: (f64, f64),
+    /// нижняя граница разброса по процентилю
+    pub low: (f64, f64),
+    /// верхняя граница разброса по процентилю
+    pub high: (f64, f64),
+}
+
+/// Изделие партии, выходящее за полосу разброса
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outlier {
+    pub name: String,
+    /// узлы вне полосы: температура и ось (`x` или `y`)
+    pub nodes: Vec<(i32, char)>,
+}
+
+/// Модели партии изделий и их статистика по узлам
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fleet {
+    /// имена изделий и их модели
+    pub devices: Vec<(String, DataFrame)>,
+    pub nodes: Vec<NodeStats>,
+    /// процентиль нижней границы разброса, верхняя граница симметрична
+    pub percentile: f64,
+}
+
+impl Fleet {
+    /// статистика по узлам первой модели, остальные модели вычисляются в этих узлах
+    pub fn new(devices: Vec<(String, DataFrame)>, percentile: f64) -> Result<Self, Box<dyn Error>> {
+        if !(0.0..=50.0).contains(&percentile) {
+            Err(format!("Invalid percentile {percentile}, expected 0..50"))?
+        }
+        let temps: Vec<i32> = devices
+            .first()
+            .ok_or("No models in the fleet")?
+            .1
+            .rows
+            .iter()
+            .filter_map(|row| row.temp)
+            .collect();
+
+        let mut nodes = vec![];
+        for temp in temps {
+            let mut values = vec![];
+            for (name, model) in devices.iter() {
+                values.push(
+                    model
+                        .eval(temp)
+                        .ok_or(format!("Empty model of device {name}"))?,
+                );
+            }
+            let x: Vec<f64> = values.iter().map(|(x, _)| *x as f64).collect();
+            let y: Vec<f64> = values.iter().map(|(_, y)| *y as f64).collect();
+
+            nodes.push(NodeStats {
+                temp,
+                mean: (mean(&x), mean(&y)),
+                std_dev: (std_dev(&x), std_dev(&y)),
+                low: (quantile(&x, percentile), quantile(&y, percentile)),
+                high: (
+                    quantile(&x, 100.0 - percentile),
+                    quantile(&y, 100.0 - percentile),
+                ),
+            });
+        }
+
+        Ok(Self {
+            devices,
+            nodes,
+            percentile,
+        })
+    }
+
+    /// изделия, кривые которых выходят за полосу разброса больше чем на `margin`
+    pub fn outliers(&self, margin: f64) -> Vec<Outlier> {
+        let outside = |value: i32, low: f64, high: f64| {
+            (value as f64) < low - margin || (value as f64) > high + margin
+        };
+
+        self.devices
+            .iter()
+            .filter_map(|(name, model)| {
+                let mut nodes = vec![];
+                for node in self.nodes.iter() {
+                    let (x, y) = model.eval(node.temp)?;
+                    if outside(x, node.low.0, node.high.0) {
+                        nodes.push((node.temp, 'x'));
+                    }
+                    if outside(y, node.low.1, node.high.1) {
+                        nodes.push((node.temp, 'y'));
+                    }
+                }
+                (!nodes.is_empty()).then(|| Outlier {
+                    name: name.clone(),
+                    nodes,
+                })
+            })
+            .collect()
+    }
+
+    /// средняя по партии модель с округлением до целых
+    pub fn mean_model(&self) -> DataFrame {
+        DataFrame {
+            rows: self
+                .nodes
+                .iter()
+                .map(|node| DataRow {
+                    temp: Some(node.temp),
+                    x: Some(node.mean.0.round() as i32),
+                    y: Some(node.mean.1.round() as i32),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// график всех кривых партии с полосой разброса, выходящие за полосу
+    /// больше чем на `margin` изделия выделяются цветом
+    pub fn plot(
+        &self,
+        path: &Path,
+        options: &PlotOptions,
+        margin: f64,
+    ) -> Result<(), Box<dyn Error>> {
+        let outliers: Vec<String> = self
+            .outliers(margin)
+            .into_iter()
+            .map(|outlier| outlier.name)
+            .collect();
+        let overlay =
+            |value: fn(&DataRow) -> Option<i32>, stats: fn(&NodeStats) -> [f64; 3]| Overlay {
+                curves: self
+                    .devices
+                    .iter()
+                    .map(|(name, model)| Curve {
+                        label: name.clone(),
+                        points: model
+                            .rows
+                            .iter()
+                            .filter_map(|row| Some((row.temp?, value(row)?)))
+                            .collect(),
+                        highlighted: outliers.contains(name),
+                    })
+                    .collect(),
+                band: self
+                    .nodes
+                    .iter()
+                    .map(|node| {
+                        let [_, low, high] = stats(node);
+                        (node.temp, low, high)
+                    })
+                    .collect(),
+                band_label: format!("P{}..P{}", self.percentile, 100.0 - self.percentile),
+                mean: self
+                    .nodes
+                    .iter()
+                    .map(|node| (node.temp, stats(node)[0]))
+                    .collect(),
+            };
+
+        plotter::plot_overlay(
+            path,
+            options,
+            &format!("{} devices", self.devices.len()),
+            overlay(|row| row.x, |node| [node.mean.0, node.low.0, node.high.0]),
+            overlay(|row| row.y, |node| [node.mean.1, node.low.1, node.high.1]),
+        )
+    }
+}
+
+impl std::fmt::Display for Fleet {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let band = format!("P{}..P{}", self.percentile, 100.0 - self.percentile);
+        write!(
+            f,
+            "| temp | mean dx | σ dx | {band} dx | mean dy | σ dy | {band} dy |\r\n"
+        )?;
+        write!(
+            f,
+            "|-----:|--------:|-----:|------:|--------:|-----:|------:|\r\n"
+        )?;
+        for node in self.nodes.iter() {
+            write!(
+                f,
+                "| {:4} | {:7.2} | {:4.2} | {:.1}..{:.1} | {:7.2} | {:4.2} | {:.1}..{:.1} |\r\n",
+                node.temp,
+                node.mean.0,
+                node.std_dev.0,
+                node.low.0,
+                node.high.0,
+                node.mean.1,
+                node.std_dev.1,
+                node.low.1,
+                node.high.1
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64).sqrt()
+}
+
+/// процентиль `p` с линейной интерполяцией между соседними значениями
+fn quantile(values: &[f64], p: f64) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let position = p / 100.0 * (sorted.len() - 1) as f64;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+#[test]
+fn fleet_stats() {
+    use std::str::FromStr;
+
+    assert_eq!(quantile(&[4.0, 1.0, 3.0, 2.0, 5.0], 50.0), 3.0);
+    assert_eq!(quantile(&[1.0, 2.0, 3.0, 4.0, 5.0], 10.0), 1.4);
+    assert_eq!(quantile(&[7.0], 5.0), 7.0);
+
+    let devices: Vec<(String, DataFrame)> = [
+        "temp;dx;dy\r\n-2;1;0\r\n4;2;0",
+        "temp;dx;dy\r\n-2;1;0\r\n4;3;0",
+        "temp;dx;dy\r\n-2;1;1\r\n4;2;0",
+        "temp;dx;dy\r\n-2;1;0\r\n4;9;0",
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, table)| (format!("#{i}"), DataFrame::from_str(table).unwrap()))
+    .collect();
+
+    let fleet = Fleet::new(devices, 25.0).unwrap();
+    assert_eq!(fleet.nodes.len(), 2);
+    assert_eq!(fleet.nodes[0].mean, (1.0, 0.25));
+    assert_eq!(fleet.nodes[1].mean.0, 4.0);
+    assert_eq!(fleet.nodes[1].low.0, 2.0);
+    assert_eq!(fleet.nodes[1].high.0, 4.5);
+
+    assert_eq!(
+        fleet.outliers(1.0),
+        vec![Outlier {
+            name: "#3".to_string(),
+            nodes: vec![(4, 'x')]
+        }]
+    );
+    assert_eq!(fleet.mean_model().eval(4), Some((4, 0)));
+
+    assert!(Fleet::new(vec![], 5.0).is_err());
+    assert!(Fleet::new(fleet.devices.clone(), 60.0).is_err());
+}
@@ -1,15 +1,137 @@
-use std::{error::Error, fs};
+use std::{
+    error::Error,
+    fs,
+    io::{Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use super::constraints::{Adjustment, Constraints};
+use super::firmware::{Firmware, Switching};
 
 mod row;
-use row::DataRow;
+pub use row::DataRow;
+mod segment;
+pub use segment::{Direction, Segment};
 
 const ABS_HEADERS: [&str; 3] = ["temp", "x", "y"];
 const DIF_HEADERS: [&str; 3] = ["temp", "dx", "dy"];
 
+/// Способ оценки значения в узле сетки по точкам в окне
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Estimator {
+    Median,
+    Mean,
+}
+
+impl std::fmt::Display for Estimator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Estimator::Median => write!(f, "median"),
+            Estimator::Mean => write!(f, "mean"),
+        }
+    }
+}
+
+impl std::str::FromStr for Estimator {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "median" => Ok(Estimator::Median),
+            "mean" => Ok(Estimator::Mean),
+            _ => Err(format!("Unknown estimator `{s}`"))?,
+        }
+    }
+}
+
+impl Estimator {
+    pub(super) fn estimate(&self, data: Vec<i32>) -> Option<i32> {
+        match self {
+            Estimator::Median => median(data),
+            Estimator::Mean => mean(data),
+        }
+    }
+
+    /// оценка по точкам с весами, при равных весах совпадает с обычной
+    pub(super) fn estimate_weighted(&self, data: Vec<(i32, f64)>) -> Option<i32> {
+        match self {
+            Estimator::Median => weighted_median(data),
+            Estimator::Mean => weighted_mean(data),
+        }
+    }
+}
+
+/// Параметры расчёта модели
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalcOptions {
+    /// первый узел сетки температур
+    pub grid_start: i32,
+    /// последний узел сетки температур
+    pub grid_end: i32,
+    pub grid_step: usize,
+    /// полуширина окна усреднения вокруг узла сетки
+    pub window: i32,
+    pub estimator: Estimator,
+    /// ограничения таблицы, применяются после оценки узлов
+    pub constraints: Constraints,
+}
+
+impl Default for CalcOptions {
+    fn default() -> Self {
+        Self {
+            grid_start: -50,
+            grid_end: 70,
+            grid_step: 6,
+            window: 3,
+            estimator: Estimator::Median,
+            constraints: Constraints::default(),
+        }
+    }
+}
+
+impl CalcOptions {
+    /// узлы сетки температур
+    pub fn grid(&self) -> impl Iterator<Item = i32> {
+        (self.grid_start..=self.grid_end).step_by(self.grid_step.max(1))
+    }
+}
+
+/// Априорная модель, например средняя по партии, для узлов с малым числом точек
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prior {
+    pub model: DataFrame,
+    /// вес априорной модели в числе точек: значение в узле с `n` точками
+    /// равно `(n * оценка + weight * априорное) / (n + weight)`
+    pub weight: u32,
+}
+
+impl Prior {
+    pub fn from_path<P: AsRef<Path>>(path: P, weight: u32) -> Result<Self, Box<dyn Error>> {
+        let model = DataFrame::from_path(&path)?;
+        if model.rows.is_empty() {
+            Err(format!("Empty prior model {}", path.as_ref().display()))?
+        }
+        Ok(Self { model, weight })
+    }
+}
+
+/// Влияние априорной модели на узел сетки
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriorInfluence {
+    None,
+    /// значение по данным смещено к априорному
+    Shrunk,
+    /// данных в узле нет, значение взято из априорной модели
+    Filled,
+}
+
 /// Структура данных для таблицы с записями
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct DataFrame {
     pub rows: Vec<DataRow>,
+    /// сегменты измерений в исходном порядке, заполняются при загрузке
+    pub segments: Vec<Segment>,
 }
 
 impl std::fmt::Display for DataFrame {
@@ -34,8 +156,11 @@ impl std::fmt::Display for DataFrame {
     }
 }
 
-impl DataFrame {
-    fn from_str(table: &str) -> Result<Self, Box<dyn Error>> {
+/// разбор таблицы с абсолютными (`temp;x;y`) или относительными (`temp;dx;dy`) координатами
+impl FromStr for DataFrame {
+    type Err = Box<dyn Error>;
+
+    fn from_str(table: &str) -> Result<Self, Self::Err> {
         let mut item = DataFrame::default();
 
         let strings: Vec<&str> = table.lines().collect();
@@ -61,15 +186,42 @@ impl DataFrame {
 
         if diff {
             item.rows.retain(|x| *x != DataRow::default());
+            if let Some(row) = item
+                .rows
+                .iter()
+                .find(|row| row.temp.is_none() || row.x.is_none() || row.y.is_none())
+            {
+                Err(format!("Invalid row format: `{}`", row))?
+            }
             item.sort();
-            Ok(item)
         } else {
-            item.to_dif()
+            item = item.to_dif()?;
         }
+        if item.rows.is_empty() {
+            Err("No data rows in the table")?
+        }
+        item.segments = segment::split(&strings[1..], diff)?;
+
+        Ok(item)
+    }
+}
+
+impl DataFrame {
+    /// является ли строка заголовком таблицы с абсолютными или относительными координатами
+    pub fn is_table_header(string: &str) -> bool {
+        let header: Vec<&str> = string.trim_end().split(';').collect();
+        header == ABS_HEADERS || header == DIF_HEADERS
+    }
+
+    /// загрузка таблицы из потока
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut table = String::new();
+        reader.read_to_string(&mut table)?;
+        DataFrame::from_str(&table)
     }
 
     /// загрузка таблицы из файла
-    pub fn from_path(path: &str) -> Result<Self, Box<dyn Error>> {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
         DataFrame::from_str(&fs::read_to_string(path)?)
     }
 
@@ -86,8 +238,16 @@ impl DataFrame {
                     x: None,
                     y: None,
                 } => {
-                    x0 = self.rows[i + 1].x.unwrap();
-                    y0 = self.rows[i + 1].y.unwrap();
+                    // сегмент отсчитывается от первой точки, у пустого сегмента в конце
+                    // файла, например только что начатого, точек ещё нет
+                    if let Some(DataRow {
+                        x: Some(x),
+                        y: Some(y),
+                        ..
+                    }) = self.rows.get(i + 1)
+                    {
+                        (x0, y0) = (*x, *y);
+                    }
                 }
                 DataRow {
                     temp: Some(temp),
@@ -109,40 +269,178 @@ impl DataFrame {
     }
 
     /// сортировка по возрастанию температуры
-    fn sort(&mut self) {
-        self.rows
-            .sort_by(|a, b| a.temp.unwrap().partial_cmp(&b.temp.unwrap()).unwrap());
+    pub(super) fn sort(&mut self) {
+        self.rows.sort_by_key(|row| row.temp);
         self.rows.dedup();
     }
 
     /// вычисление аппроксимированных координат
-    pub fn calc(&self) -> Self {
+    pub fn calc(&self, options: &CalcOptions) -> Self {
+        self.calc_nodes(options, None).0
+    }
+
+    /// вычисление аппроксимированных координат со смещением узлов с малым
+    /// числом точек к априорной модели `prior`
+    pub fn calc_with_prior(
+        &self,
+        options: &CalcOptions,
+        prior: &Prior,
+    ) -> (Self, Vec<PriorInfluence>) {
+        let (item, influence, _) = self.calc_nodes(options, Some(prior));
+        (item, influence)
+    }
+
+    /// узлы модели, влияние априорной модели и изменённые ограничениями значения
+    pub(super) fn calc_nodes(
+        &self,
+        options: &CalcOptions,
+        prior: Option<&Prior>,
+    ) -> (Self, Vec<PriorInfluence>, Vec<Adjustment>) {
         let mut item = Self::default();
+        let mut influence = vec![];
+        // без данных модель пуста, а не заполнена нулями
+        let Some(first) = self.rows.first() else {
+            return (item, influence, vec![]);
+        };
 
-        let (mut past_x, mut past_y) = (self.rows[0].x.unwrap_or(0), self.rows[0].y.unwrap_or(0));
-        for temp in (-50..=70).step_by(6) {
+        let (mut past_x, mut past_y) = (first.x.unwrap_or(0), first.y.unwrap_or(0));
+        for temp in options.grid() {
             let tail: Vec<(i32, i32)> = self
-                .rows
-                .iter()
-                .filter(|row| temp - 3 <= row.temp.unwrap() && row.temp.unwrap() <= temp + 3)
-                .map(|row| (row.x.unwrap(), row.y.unwrap()))
+                .node_rows(temp, options.window)
+                .filter_map(|row| Some((row.x?, row.y?)))
                 .collect();
 
-            let x = median(tail.iter().map(|(x, _)| *x).collect()).unwrap_or(past_x);
-            let y = median(tail.iter().map(|(_, y)| *y).collect()).unwrap_or(past_y);
+            let x = options
+                .estimator
+                .estimate(tail.iter().map(|(x, _)| *x).collect());
+            let y = options
+                .estimator
+                .estimate(tail.iter().map(|(_, y)| *y).collect());
+
+            let ((x, y), node_influence) = match (
+                x,
+                y,
+                prior.and_then(|prior| Some((prior, prior.model.eval(temp)?))),
+            ) {
+                (Some(x), Some(y), Some((prior, (prior_x, prior_y)))) => {
+                    let shrink = |value: i32, prior_value: i32| {
+                        let (n, k) = (tail.len() as f64, prior.weight as f64);
+                        ((n * value as f64 + k * prior_value as f64) / (n + k)).round() as i32
+                    };
+                    let shrunk = (shrink(x, prior_x), shrink(y, prior_y));
+                    if shrunk == (x, y) {
+                        ((x, y), PriorInfluence::None)
+                    } else {
+                        (shrunk, PriorInfluence::Shrunk)
+                    }
+                }
+                (Some(x), Some(y), None) => ((x, y), PriorInfluence::None),
+                (_, _, Some((_, prior_value))) => (prior_value, PriorInfluence::Filled),
+                (_, _, None) => ((past_x, past_y), PriorInfluence::None),
+            };
 
             item.rows.push(DataRow {
                 temp: Some(temp),
                 x: Some(x),
                 y: Some(y),
             });
+            influence.push(node_influence);
             (past_x, past_y) = (x, y);
         }
+        let adjustments = options.constraints.apply(&mut item);
+        (item, influence, adjustments)
+    }
+
+    /// строки в окне узла сетки `temp`
+    pub(super) fn node_rows(&self, temp: i32, window: i32) -> impl Iterator<Item = &DataRow> {
+        self.rows.iter().filter(move |row| {
+            row.temp.map_or(false, |row_temp| {
+                temp - window <= row_temp && row_temp <= temp + window
+            })
+        })
+    }
+
+    /// количество точек в окне каждого узла сетки
+    pub fn coverage(&self, options: &CalcOptions) -> Vec<(i32, usize)> {
+        options
+            .grid()
+            .map(|temp| (temp, self.node_rows(temp, options.window).count()))
+            .collect()
+    }
+
+    /// диапазоны температур соседних узлов сетки, в окне которых меньше `min_points` точек
+    pub fn gaps(&self, options: &CalcOptions, min_points: usize) -> Vec<(i32, i32)> {
+        let mut gaps: Vec<(i32, i32)> = vec![];
+        let mut in_gap = false;
+        for (temp, count) in self.coverage(options) {
+            if count >= min_points {
+                in_gap = false;
+                continue;
+            }
+            match gaps.last_mut() {
+                Some(gap) if in_gap => gap.1 = temp,
+                _ => gaps.push((temp, temp)),
+            }
+            in_gap = true;
+        }
+        gaps
+    }
+
+    /// значение ступенчатой модели при температуре `temp`,
+    /// переключение происходит посередине между узлами
+    pub fn eval(&self, temp: i32) -> Option<(i32, i32)> {
+        Some(
+            Firmware::new(self, Switching::Midpoint)
+                .ok()?
+                .lookup(temp as f64),
+        )
+    }
+
+    /// отклонения сырых данных от модели `model`
+    pub fn residuals(&self, model: &DataFrame) -> Self {
+        self.residuals_with(model, Switching::Midpoint)
+    }
+
+    /// отклонения сырых данных от модели `model` при переключении ступеней по правилу
+    /// `switching`; при гистерезисе ступень зависит от направления, поэтому сегменты
+    /// проходятся в порядке измерения
+    pub fn residuals_with(&self, model: &DataFrame, switching: Switching) -> Self {
+        let mut item = Self::default();
+        let Ok(mut firmware) = Firmware::new(model, switching) else {
+            return item;
+        };
+        let passes: Vec<&[DataRow]> = match switching {
+            Switching::Hysteresis(_) if !self.segments.is_empty() => self
+                .segments
+                .iter()
+                .map(|segment| segment.rows.as_slice())
+                .collect(),
+            _ => vec![&self.rows],
+        };
+        for rows in passes {
+            firmware.reset();
+            for row in rows.iter() {
+                if let (Some(temp), Some(x), Some(y)) = (row.temp, row.x, row.y) {
+                    let (model_x, model_y) = firmware.lookup(temp as f64);
+                    item.rows.push(DataRow {
+                        temp: Some(temp),
+                        x: Some(x - model_x),
+                        y: Some(y - model_y),
+                    });
+                }
+            }
+        }
         item
     }
 
+    /// запись таблицы в поток
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), Box<dyn Error>> {
+        writer.write_all(self.to_string().as_bytes())?;
+        Ok(())
+    }
+
     /// сохранить csv файл
-    pub fn save_file(&self, path: &str) -> Result<(), Box<dyn Error>> {
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
         fs::write(path, self.to_string().as_bytes())?;
         Ok(())
     }
@@ -162,6 +460,94 @@ fn median(mut data: Vec<i32>) -> Option<i32> {
     }
 }
 
+/// Статистика отклонений от модели
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub struct ResidualStats {
+    pub count: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub max_abs: i32,
+    /// доля точек в пределах допуска, %
+    pub within: f64,
+}
+
+impl std::fmt::Display for ResidualStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "n = {}, mean = {:.2}, σ = {:.2}, max = {}, in tolerance = {:.1}%",
+            self.count, self.mean, self.std_dev, self.max_abs, self.within
+        )
+    }
+}
+
+impl ResidualStats {
+    pub fn new(residuals: &[i32], tolerance: i32) -> Self {
+        if residuals.is_empty() {
+            return Self::default();
+        }
+
+        let count = residuals.len();
+        let mean = residuals.iter().sum::<i32>() as f64 / count as f64;
+        let variance = residuals
+            .iter()
+            .map(|r| (*r as f64 - mean).powi(2))
+            .sum::<f64>()
+            / count as f64;
+        let within = residuals.iter().filter(|r| r.abs() <= tolerance).count();
+
+        Self {
+            count,
+            mean,
+            std_dev: variance.sqrt(),
+            max_abs: residuals.iter().map(|r| r.abs()).max().unwrap_or(0),
+            within: 100.0 * within as f64 / count as f64,
+        }
+    }
+}
+
+fn mean(data: Vec<i32>) -> Option<i32> {
+    if data.is_empty() {
+        None?
+    }
+
+    Some((data.iter().sum::<i32>() as f64 / data.len() as f64).round() as i32)
+}
+
+/// значение, на котором накопленный вес достигает половины общего
+fn weighted_median(mut data: Vec<(i32, f64)>) -> Option<i32> {
+    if data.is_empty() {
+        None?
+    }
+
+    data.sort_by_key(|(value, _)| *value);
+    let half = data.iter().map(|(_, weight)| weight).sum::<f64>() / 2.0;
+    let mut accumulated = 0.0;
+    for (i, (value, weight)) in data.iter().enumerate() {
+        accumulated += weight;
+        if accumulated == half && i + 1 < data.len() {
+            return Some((value + data[i + 1].0) / 2);
+        }
+        if accumulated > half {
+            return Some(*value);
+        }
+    }
+    data.last().map(|(value, _)| *value)
+}
+
+fn weighted_mean(data: Vec<(i32, f64)>) -> Option<i32> {
+    let total = data.iter().map(|(_, weight)| weight).sum::<f64>();
+    if data.is_empty() || total == 0.0 {
+        None?
+    }
+
+    let sum = data
+        .iter()
+        .map(|(value, weight)| *value as f64 * weight)
+        .sum::<f64>();
+    Some((sum / total).round() as i32)
+}
+
 #[test]
 fn string_to_frame() {
     let table = "temp;x;y\r\n12;34;56";
@@ -196,6 +582,20 @@ fn string_to_frame() {
             y: Some(0)
         }]
     );
+
+    // только что начатый сегмент в конце файла не сдвигает прежние точки
+    let table = "temp;x;y\r\n# plus;;\r\n12;34;56\r\n# minus;;";
+    let frame = DataFrame::from_str(table).unwrap();
+    assert_eq!(frame.rows[0].x, Some(0));
+    assert_eq!(frame.segments.len(), 2);
+    assert!(DataFrame::from_str("temp;x;y\r\n# plus 01.01.2024;;").is_err());
+    // неполная строка — ошибка разбора, а не паника при сортировке
+    assert!(DataFrame::from_str("temp;dx;dy\r\n12;34;56\r\nnan;1;2").is_err());
+    assert!(DataFrame::from_str("temp;dx;dy\r\n12;;56").is_err());
+    assert!(DataFrame::default()
+        .calc(&CalcOptions::default())
+        .rows
+        .is_empty());
 }
 
 #[test]
@@ -206,6 +606,7 @@ fn frame_to_string() {
             x: Some(34),
             y: Some(56),
         }],
+        ..Default::default()
     };
     assert_eq!(frame.to_string(), "temp;dx;dy\r\n12;34;56".to_string());
 
@@ -215,6 +616,7 @@ fn frame_to_string() {
             x: Some(34),
             y: Some(56),
         }],
+        ..Default::default()
     };
     assert_eq!(frame.to_string(), "temp;dx;dy\r\n12;34;56".to_string());
 
@@ -224,6 +626,7 @@ fn frame_to_string() {
             x: Some(34),
             y: Some(56),
         }],
+        ..Default::default()
     };
     assert_eq!(frame.to_string(), "temp;dx;dy\r\nnan;34;56".to_string());
 }
@@ -653,14 +1056,145 @@ fn file_to_frame() {
 fn calc_auto_model() {
     DataFrame::from_path("test/test_data.csv")
         .unwrap()
-        .calc()
+        .calc(&CalcOptions::default())
         .save_file("test/test_data_auto_model.txt")
         .unwrap();
 }
 
 #[test]
 fn test_auto_model() {
-    let test_auto_model = DataFrame::from_path("test/test_data.csv").unwrap().calc();
+    let test_auto_model = DataFrame::from_path("test/test_data.csv")
+        .unwrap()
+        .calc(&CalcOptions::default());
     let auto_model = DataFrame::from_path("test/test_data_auto_model.txt").unwrap();
     assert_eq!(test_auto_model.rows, auto_model.rows);
 }
+
+#[test]
+fn model_eval() {
+    let model = DataFrame::from_str("temp;dx;dy\r\n-2;1;-1\r\n4;2;-2\r\n10;3;-3").unwrap();
+    assert_eq!(model.eval(-50), Some((1, -1)));
+    assert_eq!(model.eval(0), Some((1, -1)));
+    assert_eq!(model.eval(1), Some((2, -2)));
+    assert_eq!(model.eval(6), Some((2, -2)));
+    assert_eq!(model.eval(7), Some((3, -3)));
+    assert_eq!(model.eval(70), Some((3, -3)));
+    assert_eq!(DataFrame::default().eval(0), None);
+
+    let raw = DataFrame::from_str("temp;dx;dy\r\n0;2;-1\r\n5;0;0").unwrap();
+    assert_eq!(
+        raw.residuals(&model).rows,
+        vec![
+            DataRow {
+                temp: Some(0),
+                x: Some(1),
+                y: Some(0)
+            },
+            DataRow {
+                temp: Some(5),
+                x: Some(-2),
+                y: Some(2)
+            }
+        ]
+    );
+
+    let stats = ResidualStats::new(&[1, -2], 1);
+    assert_eq!(stats.count, 2);
+    assert_eq!(stats.max_abs, 2);
+    assert_eq!(stats.within, 50.0);
+}
+
+#[test]
+fn frame_coverage() {
+    let frame = DataFrame::from_path("test/test_data.csv").unwrap();
+    let coverage = frame.coverage(&CalcOptions::default());
+    assert_eq!(coverage.len(), 21);
+    assert_eq!(coverage[0], (-50, 1));
+    assert_eq!(coverage[1], (-44, 5));
+    assert_eq!(
+        frame.gaps(&CalcOptions::default(), 3),
+        vec![(-50, -50), (70, 70)]
+    );
+    assert_eq!(
+        frame.gaps(&CalcOptions::default(), 4),
+        vec![(-50, -50), (-38, 22), (70, 70)]
+    );
+    assert!(frame.gaps(&CalcOptions::default(), 1).is_empty());
+}
+
+#[test]
+fn calc_with_options() {
+    let frame = DataFrame::from_str("temp;dx;dy\r\n0;0;0\r\n1;1;3\r\n2;1;4\r\n10;5;5").unwrap();
+    let options = CalcOptions {
+        grid_start: 0,
+        grid_end: 10,
+        grid_step: 5,
+        window: 2,
+        estimator: Estimator::Mean,
+        constraints: Constraints::default(),
+    };
+    let calc = frame.calc(&options);
+    assert_eq!(
+        calc.rows,
+        vec![
+            DataRow {
+                temp: Some(0),
+                x: Some(1),
+                y: Some(2)
+            },
+            DataRow {
+                temp: Some(5),
+                x: Some(1),
+                y: Some(2)
+            },
+            DataRow {
+                temp: Some(10),
+                x: Some(5),
+                y: Some(5)
+            }
+        ]
+    );
+    assert_eq!("mean".parse::<Estimator>().unwrap(), Estimator::Mean);
+    assert!("mode".parse::<Estimator>().is_err());
+}
+
+#[test]
+fn calc_with_prior() {
+    let frame = DataFrame::from_str("temp;dx;dy\r\n0;0;0\r\n1;4;4\r\n2;4;4\r\n3;4;4").unwrap();
+    let options = CalcOptions {
+        grid_start: 0,
+        grid_end: 10,
+        grid_step: 5,
+        window: 2,
+        estimator: Estimator::Median,
+        constraints: Constraints::default(),
+    };
+    let prior = Prior {
+        model: DataFrame::from_str("temp;dx;dy\r\n0;0;4\r\n5;1;1\r\n10;2;2").unwrap(),
+        weight: 3,
+    };
+
+    let (calc, influence) = frame.calc_with_prior(&options, &prior);
+    assert_eq!(
+        calc.rows
+            .iter()
+            .map(|row| (row.x, row.y))
+            .collect::<Vec<_>>(),
+        vec![(Some(2), Some(4)), (Some(2), Some(2)), (Some(2), Some(2))]
+    );
+    assert_eq!(
+        influence,
+        vec![
+            PriorInfluence::Shrunk,
+            PriorInfluence::Shrunk,
+            PriorInfluence::Filled
+        ]
+    );
+
+    let prior = Prior { weight: 0, ..prior };
+    let (calc, influence) = frame.calc_with_prior(&options, &prior);
+    assert_eq!(calc.rows[0].x, Some(4));
+    assert_eq!(influence[0], PriorInfluence::None);
+    assert_eq!(influence[2], PriorInfluence::Filled);
+    assert_eq!(frame.calc(&options).rows[2].x, Some(4));
+}
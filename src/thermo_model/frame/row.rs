@@ -1,4 +1,5 @@
 use std::error::Error;
+use std::str::FromStr;
 
 /// Структура данных для отдельной строки csv файла
 #[derive(Default, Debug, PartialEq, Eq, Clone)]
@@ -29,8 +30,10 @@ impl std::fmt::Display for DataRow {
     }
 }
 
-impl DataRow {
-    pub fn from_str(string: &str) -> Result<Self, Box<dyn Error>> {
+impl FromStr for DataRow {
+    type Err = Box<dyn Error>;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
         let chunks: Vec<&str> = string.split(';').collect();
         if chunks.len() != 3 {
             Err(format!("Error! Invalid row length {}", chunks.len()))?
@@ -0,0 +1,146 @@
+use std::error::Error;
+use std::str::FromStr;
+
+use super::DataRow;
+
+/// Направление изменения температуры в сегменте измерений
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Direction {
+    Plus,
+    Minus,
+    Unknown,
+}
+
+impl Direction {
+    /// определение направления по тексту комментария сегмента
+    pub fn from_comment(comment: &str) -> Self {
+        let comment = comment.to_lowercase();
+        let first = comment.split_whitespace().next().unwrap_or_default();
+
+        if first == "+" || comment.contains("plus") || comment.contains("плюс") {
+            Direction::Plus
+        } else if first == "-" || comment.contains("minus") || comment.contains("минус") {
+            Direction::Minus
+        } else {
+            Direction::Unknown
+        }
+    }
+}
+
+/// Сегмент измерений: строки между двумя строками-комментариями
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct Segment {
+    /// текст комментария без `#` и разделителей, например `plus 24.11.2021`
+    pub comment: String,
+    pub rows: Vec<DataRow>,
+}
+
+impl Segment {
+    pub fn direction(&self) -> Direction {
+        Direction::from_comment(&self.comment)
+    }
+}
+
+/// текст комментария из строки вида `# plus 24.11.2021;;`
+pub fn comment_text(string: &str) -> String {
+    string
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches('#')
+        .trim()
+        .to_string()
+}
+
+/// разбиение строк таблицы на сегменты, для абсолютных координат
+/// значения отсчитываются от первой строки сегмента
+pub fn split(strings: &[&str], diff: bool) -> Result<Vec<Segment>, Box<dyn Error>> {
+    let mut segments: Vec<Segment> = vec![];
+    let (mut x0, mut y0) = (0, 0);
+    let mut new_segment = false;
+
+    for string in strings {
+        match DataRow::from_str(string)? {
+            DataRow {
+                temp: None,
+                x: None,
+                y: None,
+            } => {
+                segments.push(Segment {
+                    comment: comment_text(string),
+                    rows: vec![],
+                });
+                new_segment = true;
+            }
+            DataRow {
+                temp: Some(temp),
+                x: Some(x),
+                y: Some(y),
+            } => {
+                if segments.is_empty() {
+                    segments.push(Segment::default());
+                }
+                if new_segment && !diff {
+                    (x0, y0) = (x, y);
+                }
+                new_segment = false;
+
+                segments.last_mut().unwrap().rows.push(DataRow {
+                    temp: Some(temp),
+                    x: Some(x - x0),
+                    y: Some(y - y0),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(segments)
+}
+
+#[test]
+fn comment_to_direction() {
+    assert_eq!(comment_text("# plus 24.11.2021;;"), "plus 24.11.2021");
+    assert_eq!(Direction::from_comment("plus 24.11.2021"), Direction::Plus);
+    assert_eq!(
+        Direction::from_comment("minus 07.09 narrow"),
+        Direction::Minus
+    );
+    assert_eq!(Direction::from_comment("Минус 07.09"), Direction::Minus);
+    assert_eq!(Direction::from_comment("+ 07.09"), Direction::Plus);
+    assert_eq!(Direction::from_comment("07.09"), Direction::Unknown);
+}
+
+#[test]
+fn strings_to_segments() {
+    let strings = [
+        "# plus 24.11.2021;;",
+        "23;2;-52",
+        "25;3;-52",
+        "# minus;;",
+        "60;5;5",
+        "58;6;4",
+    ];
+    let segments = split(&strings, false).unwrap();
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0].comment, "plus 24.11.2021");
+    assert_eq!(segments[0].direction(), Direction::Plus);
+    assert_eq!(
+        segments[1].rows,
+        vec![
+            DataRow {
+                temp: Some(60),
+                x: Some(0),
+                y: Some(0)
+            },
+            DataRow {
+                temp: Some(58),
+                x: Some(1),
+                y: Some(-1)
+            }
+        ]
+    );
+
+    let segments = split(&strings, true).unwrap();
+    assert_eq!(segments[1].rows[0].x, Some(5));
+}
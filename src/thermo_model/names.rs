@@ -0,0 +1,194 @@
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+use super::Artifact;
+
+/// Шаблоны имён выходных файлов
+///
+/// Поддерживаются подстановки `{stem}` (имя исходного файла без расширения),
+/// `{serial}` (серийный номер), `{date}` (дата расчёта) и `{ext}` (расширение графика).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplates {
+    pub plot: String,
+    pub md: String,
+    pub auto_model: String,
+    pub ct: String,
+    /// объединённые сырые данные `update`, не выходной файл расчёта
+    pub merged: String,
+}
+
+impl Default for NameTemplates {
+    fn default() -> Self {
+        Self {
+            plot: "{stem}_with_model.{ext}".to_string(),
+            md: "{stem}_model.md".to_string(),
+            auto_model: "{stem}_auto_model.txt".to_string(),
+            ct: "tpk-k_{serial}_{date}.ct".to_string(),
+            merged: "{stem}_merged.csv".to_string(),
+        }
+    }
+}
+
+impl NameTemplates {
+    pub fn get(&self, artifact: Artifact) -> &str {
+        match artifact {
+            Artifact::Plot => &self.plot,
+            Artifact::Md => &self.md,
+            Artifact::AutoModel => &self.auto_model,
+            Artifact::Ct => &self.ct,
+        }
+    }
+
+    pub fn set(&mut self, artifact: Artifact, template: &str) {
+        let template = template.to_string();
+        match artifact {
+            Artifact::Plot => self.plot = template,
+            Artifact::Md => self.md = template,
+            Artifact::AutoModel => self.auto_model = template,
+            Artifact::Ct => self.ct = template,
+        }
+    }
+
+    /// разбор шаблона в формате `ВЫХОДНОЙ_ФАЙЛ=ШАБЛОН`, например `md={stem}_model.md`,
+    /// шаблон объединённых данных задаётся как `merged={stem}_merged.csv`
+    pub fn set_from_str(&mut self, s: &str) -> Result<(), Box<dyn Error>> {
+        let (artifact, template) = s.split_once('=').ok_or(format!(
+            "Invalid name template `{s}`, expected OUTPUT=TEMPLATE"
+        ))?;
+        if template.is_empty() {
+            Err(format!("Empty name template for `{artifact}`"))?
+        }
+
+        match artifact.trim() {
+            "merged" => self.merged = template.to_string(),
+            artifact => self.set(artifact.parse()?, template),
+        }
+        Ok(())
+    }
+
+    /// подходит ли имя файла `name` под шаблон выходного файла, подстановки
+    /// соответствуют любому тексту
+    pub fn matches(&self, artifact: Artifact, name: &str) -> bool {
+        matches(self.get(artifact), name)
+    }
+
+    /// подходит ли имя файла `name` под шаблон объединённых данных
+    pub fn is_merged(&self, name: &str) -> bool {
+        matches(&self.merged, name)
+    }
+}
+
+/// подходит ли имя файла `name` под шаблон `template`
+fn matches(template: &str, name: &str) -> bool {
+    let pattern = template
+        .split('{')
+        .enumerate()
+        .map(|(i, part)| match (i, part.split_once('}')) {
+            (0, _) | (_, None) => regex::escape(part),
+            (_, Some((_, rest))) => format!(".*{}", regex::escape(rest)),
+        })
+        .collect::<String>();
+
+    regex::Regex::new(&format!("^{pattern}$")).map_or(false, |re| re.is_match(name))
+}
+
+/// подстановка значений `vars` в шаблон; значения не обязаны быть в UTF-8,
+/// например имя исходного файла, поэтому имя собирается как `OsString`
+pub fn render(template: &str, vars: &[(&str, &OsStr)]) -> OsString {
+    let mut name = OsString::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let value = rest[start..].find('}').and_then(|end| {
+            let key = &rest[start + 1..start + end];
+            let (_, value) = vars.iter().find(|(name, _)| *name == key)?;
+            Some((value, start + end + 1))
+        });
+        match value {
+            Some((value, end)) => {
+                name.push(&rest[..start]);
+                name.push(value);
+                rest = &rest[end..];
+            }
+            None => {
+                name.push(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    name.push(rest);
+    name
+}
+
+/// имя исходного файла без расширения, учитывается только последняя точка
+pub fn stem(path: &Path) -> Result<OsString, Box<dyn Error>> {
+    Ok(path
+        .file_stem()
+        .ok_or(format!("Invalid file name: `{}`", path.display()))?
+        .to_os_string())
+}
+
+/// папка, в которой лежит файл `path`
+pub fn parent(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+#[test]
+fn paths_and_templates() {
+    let path = Path::new("../runs/v1.2/data.csv");
+    assert_eq!(stem(path).unwrap(), "data");
+    assert_eq!(parent(path), PathBuf::from("../runs/v1.2"));
+
+    let path = Path::new("/данные/2БЛ123/прогон.1.csv");
+    assert_eq!(stem(path).unwrap(), "прогон.1");
+    assert_eq!(parent(path), PathBuf::from("/данные/2БЛ123"));
+    assert_eq!(parent(Path::new("data.csv")), PathBuf::from("."));
+
+    let mut names = NameTemplates::default();
+    assert_eq!(
+        render(&names.md, &[("stem", OsStr::new("data"))]),
+        "data_model.md"
+    );
+    assert_eq!(
+        render("{stem}_{x}.{", &[("stem", OsStr::new("{x}"))]),
+        "{x}_{x}.{"
+    );
+
+    names.set_from_str("ct={serial}_{stem}.ct").unwrap();
+    assert_eq!(
+        render(
+            names.get(Artifact::Ct),
+            &[
+                ("stem", OsStr::new("data")),
+                ("serial", OsStr::new("2БЛ123"))
+            ]
+        ),
+        "2БЛ123_data.ct"
+    );
+    assert!(names.matches(Artifact::Ct, "2БЛ123_data.ct"));
+    assert!(!names.matches(Artifact::Ct, "2БЛ123_data.ct.bak"));
+    assert!(names.matches(Artifact::AutoModel, "run.1_auto_model.txt"));
+    assert!(!names.matches(Artifact::AutoModel, "run.1.txt"));
+    assert!(names.is_merged("run.1_merged.csv"));
+    assert!(!names.is_merged("run.1.csv"));
+    names.set_from_str("merged=merged_{stem}.csv").unwrap();
+    assert!(names.is_merged("merged_run.csv"));
+    assert!(names.set_from_str("ct").is_err());
+    assert!(names.set_from_str("png=x").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_names() {
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+    let path = Path::new(OsStr::from_bytes(b"/data/run\xff.csv"));
+    let name = render(
+        &NameTemplates::default().md,
+        &[("stem", &stem(path).unwrap())],
+    );
+    assert_eq!(name.into_vec(), b"run\xff_model.md");
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+use super::DataFrame;
+
+/// Ручная правка значения узла модели
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Override {
+    pub temp: i32,
+    /// новое значение по оси x, если не задано, то остаётся расчётное
+    pub x: Option<i32>,
+    /// новое значение по оси y, если не задано, то остаётся расчётное
+    pub y: Option<i32>,
+    /// кто изменил узел
+    pub author: String,
+    /// причина изменения
+    pub reason: String,
+}
+
+/// Файл правок: таблицы `[[override]]`
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+struct OverridesFile {
+    #[serde(rename = "override")]
+    overrides: Vec<Override>,
+}
+
+impl Override {
+    /// правка из строки вида `ТЕМПЕРАТУРА=X,Y`, одно из значений можно опустить: `52=,-20`
+    pub fn parse(s: &str, author: &str, reason: &str) -> Result<Self, Box<dyn Error>> {
+        let invalid = || format!("Invalid override `{s}`, expected TEMP=X,Y");
+        let (temp, values) = s.split_once('=').ok_or_else(invalid)?;
+        let (x, y) = values.split_once(',').ok_or_else(invalid)?;
+        let value = |value: &str| -> Result<Option<i32>, Box<dyn Error>> {
+            match value.trim() {
+                "" => Ok(None),
+                value => Ok(Some(value.parse().map_err(|_| invalid())?)),
+            }
+        };
+
+        let item = Self {
+            temp: temp.trim().parse().map_err(|_| invalid())?,
+            x: value(x)?,
+            y: value(y)?,
+            author: author.trim().to_string(),
+            reason: reason.trim().to_string(),
+        };
+        item.check()?;
+        Ok(item)
+    }
+
+    /// правки из TOML файла
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, Box<dyn Error>> {
+        let path = path.as_ref();
+        let file: OverridesFile = toml::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|err| format!("Invalid overrides {}: {err}", path.display()))?;
+        for item in file.overrides.iter() {
+            item.check()?;
+        }
+        Ok(file.overrides)
+    }
+
+    /// замена значения узла в модели `model`, возвращает расчётное значение
+    pub fn apply(&self, model: &mut DataFrame) -> Result<(i32, i32), Box<dyn Error>> {
+        let row = model
+            .rows
+            .iter_mut()
+            .find(|row| row.temp == Some(self.temp))
+            .ok_or(format!("No model node at {} to override", self.temp))?;
+        let calculated = (row.x.unwrap_or(0), row.y.unwrap_or(0));
+
+        row.x = Some(self.x.unwrap_or(calculated.0));
+        row.y = Some(self.y.unwrap_or(calculated.1));
+        Ok(calculated)
+    }
+
+    /// описание правки с расчётным значением узла `calculated`
+    pub fn describe(&self, calculated: (i32, i32)) -> String {
+        let change = |name: &str, old: i32, new: Option<i32>| match new {
+            Some(new) => format!(", {name} {old} → {new}"),
+            None => String::default(),
+        };
+        format!(
+            "{}{}{} by {}: {}",
+            self.temp,
+            change("dx", calculated.0, self.x),
+            change("dy", calculated.1, self.y),
+            self.author,
+            self.reason
+        )
+    }
+
+    fn check(&self) -> Result<(), Box<dyn Error>> {
+        if self.x.is_none() && self.y.is_none() {
+            Err(format!("Override of node {} sets no value", self.temp))?
+        }
+        if self.author.trim().is_empty() || self.reason.trim().is_empty() {
+            Err(format!(
+                "Override of node {} needs an author and a reason",
+                self.temp
+            ))?
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn node_overrides() {
+    let item = Override::parse("52=,-20", "Иванов", "ступенька").unwrap();
+    assert_eq!((item.temp, item.x, item.y), (52, None, Some(-20)));
+    assert!(Override::parse("52=1", "Иванов", "ступенька").is_err());
+    assert!(Override::parse("52=,", "Иванов", "ступенька").is_err());
+    assert!(Override::parse("52=1,2", "Иванов", " ").is_err());
+
+    let mut model: DataFrame = "temp;dx;dy\r\n46;1;-18\r\n52;2;-17".parse().unwrap();
+    assert_eq!(item.apply(&mut model).unwrap(), (2, -17));
+    assert_eq!(model.eval(52), Some((2, -20)));
+    assert_eq!(
+        item.describe((2, -17)),
+        "52, dy -17 → -20 by Иванов: ступенька"
+    );
+    assert!(Override::parse("53=1,1", "Иванов", "ступенька")
+        .unwrap()
+        .apply(&mut model)
+        .is_err());
+
+    let path = std::env::temp_dir().join(format!(
+        "r-approx-overrides-test-{}.toml",
+        std::process::id()
+    ));
+    std::fs::write(
+        &path,
+        "[[override]]\ntemp = 46\nx = 0\nauthor = \"Петров\"\nreason = \"сглаживание\"\n",
+    )
+    .unwrap();
+    let overrides = Override::from_path(&path).unwrap();
+    assert_eq!(overrides.len(), 1);
+    assert_eq!(overrides[0].x, Some(0));
+    std::fs::remove_file(&path).unwrap();
+}
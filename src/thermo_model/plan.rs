@@ -0,0 +1,258 @@
+use std::error::Error;
+use std::io::{Read, Write};
+
+use super::{CalcOptions, DataFrame};
+
+/// Параметры плана измерений
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlanOptions {
+    /// наименьшее число точек в окне покрытого узла
+    pub min_points: usize,
+    /// наибольший разброс значений в окне узла по любой из осей
+    pub max_spread: i32,
+    /// время установления температуры в камере, мин
+    pub settle_minutes: u32,
+    /// время снятия одной точки, мин
+    pub sample_minutes: u32,
+    /// скорость изменения температуры камеры, °C/ч
+    pub ramp_rate: u32,
+}
+
+impl Default for PlanOptions {
+    fn default() -> Self {
+        Self {
+            min_points: 3,
+            max_spread: 2,
+            settle_minutes: 30,
+            sample_minutes: 5,
+            ramp_rate: 60,
+        }
+    }
+}
+
+/// Причина повторного измерения в узле
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// в окне узла нет точек
+    Missing,
+    /// точек меньше `min_points`
+    Few,
+    /// разброс точек больше `max_spread`
+    Spread,
+}
+
+impl std::fmt::Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Reason::Missing => write!(f, "no data"),
+            Reason::Few => write!(f, "few points"),
+            Reason::Spread => write!(f, "spread"),
+        }
+    }
+}
+
+/// Уставка камеры в плане измерений
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Setpoint {
+    pub temp: i32,
+    /// число точек в окне узла
+    pub count: usize,
+    /// наибольший по осям разброс точек в окне узла
+    pub spread: i32,
+    /// число точек, которые нужно снять
+    pub points: usize,
+    /// время выдержки на уставке, мин
+    pub dwell: u32,
+    pub reason: Reason,
+}
+
+/// План дополнительных измерений: узлы сетки с недостаточным числом точек
+/// или большим разбросом, по возрастанию температуры
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub setpoints: Vec<Setpoint>,
+    pub options: PlanOptions,
+}
+
+impl Plan {
+    pub fn new(raw: &DataFrame, calc: &CalcOptions, options: &PlanOptions) -> Self {
+        let mut setpoints = vec![];
+        for temp in calc.grid() {
+            let rows: Vec<_> = raw.node_rows(temp, calc.window).collect();
+            let range = |values: Vec<i32>| {
+                values.iter().max().unwrap_or(&0) - values.iter().min().unwrap_or(&0)
+            };
+            let spread = range(rows.iter().filter_map(|row| row.x).collect())
+                .max(range(rows.iter().filter_map(|row| row.y).collect()));
+
+            let (reason, points) = match rows.len() {
+                0 => (Reason::Missing, options.min_points),
+                count if count < options.min_points => (Reason::Few, options.min_points - count),
+                // при большом разбросе снимается столько же новых точек, сколько нужно для покрытия
+                _ if spread > options.max_spread => (Reason::Spread, options.min_points),
+                _ => continue,
+            };
+            setpoints.push(Setpoint {
+                temp,
+                count: rows.len(),
+                spread,
+                points,
+                dwell: options.settle_minutes + points as u32 * options.sample_minutes,
+                reason,
+            });
+        }
+        Self {
+            setpoints,
+            options: *options,
+        }
+    }
+
+    /// общее время плана, мин: выдержки на уставках и переходы между ними
+    pub fn duration(&self) -> u32 {
+        let dwell: u32 = self.setpoints.iter().map(|setpoint| setpoint.dwell).sum();
+        let ramp: u32 = self
+            .setpoints
+            .windows(2)
+            .map(|pair| pair[0].temp.abs_diff(pair[1].temp))
+            .sum();
+        dwell + (ramp as f64 * 60.0 / self.options.ramp_rate.max(1) as f64).ceil() as u32
+    }
+
+    /// запись плана для скрипта управления камерой: `setpoint;points;dwell_min;reason`
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), Box<dyn Error>> {
+        writeln!(writer, "setpoint;points;dwell_min;reason")?;
+        for setpoint in self.setpoints.iter() {
+            writeln!(
+                writer,
+                "{};{};{};{}",
+                setpoint.temp, setpoint.points, setpoint.dwell, setpoint.reason
+            )?;
+        }
+        Ok(())
+    }
+
+    /// уставки и число точек из файла плана, записанного `write_csv`
+    pub fn read_setpoints<R: Read>(mut reader: R) -> Result<Vec<(i32, usize)>, Box<dyn Error>> {
+        let mut table = String::new();
+        reader.read_to_string(&mut table)?;
+        let mut lines = table.lines();
+        if !lines
+            .next()
+            .unwrap_or_default()
+            .starts_with("setpoint;points")
+        {
+            Err("Invalid plan header, expected `setpoint;points;...`")?
+        }
+
+        let mut setpoints = vec![];
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let mut values = line.split(';');
+            let invalid = || format!("Invalid plan row `{line}`");
+            let temp = values.next().unwrap_or_default().trim().parse();
+            let points = values.next().unwrap_or_default().trim().parse();
+            match (temp, points) {
+                (Ok(temp), Ok(points)) => setpoints.push((temp, points)),
+                _ => Err(invalid())?,
+            }
+        }
+        Ok(setpoints)
+    }
+}
+
+impl std::fmt::Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "| temp | n | spread | points | dwell, min | reason |\r\n"
+        )?;
+        write!(
+            f,
+            "|-----:|--:|-------:|-------:|-----------:|--------|\r\n"
+        )?;
+        for setpoint in self.setpoints.iter() {
+            write!(
+                f,
+                "| {:4} | {} | {:6} | {:6} | {:10} | {} |\r\n",
+                setpoint.temp,
+                setpoint.count,
+                setpoint.spread,
+                setpoint.points,
+                setpoint.dwell,
+                setpoint.reason
+            )?;
+        }
+        let duration = self.duration();
+        write!(
+            f,
+            "setpoints: {}, total: {}h {:02}min\r\n",
+            self.setpoints.len(),
+            duration / 60,
+            duration % 60
+        )
+    }
+}
+
+#[test]
+fn measurement_plan() {
+    let raw: DataFrame =
+        "temp;dx;dy\r\n0;0;0\r\n1;0;1\r\n-1;0;0\r\n6;1;1\r\n12;0;0\r\n12;4;0\r\n13;1;0"
+            .parse()
+            .unwrap();
+    let calc = CalcOptions {
+        grid_start: 0,
+        grid_end: 18,
+        grid_step: 6,
+        window: 1,
+        ..Default::default()
+    };
+    let plan = Plan::new(&raw, &calc, &PlanOptions::default());
+    let reasons: Vec<_> = plan
+        .setpoints
+        .iter()
+        .map(|setpoint| (setpoint.temp, setpoint.points, setpoint.reason))
+        .collect();
+    assert_eq!(
+        reasons,
+        vec![
+            (6, 2, Reason::Few),
+            (12, 3, Reason::Spread),
+            (18, 3, Reason::Missing)
+        ]
+    );
+    assert_eq!(plan.setpoints[0].dwell, 40);
+    assert_eq!(plan.duration(), 40 + 45 + 45 + 12);
+
+    let mut table = vec![];
+    plan.write_csv(&mut table).unwrap();
+    let table = String::from_utf8(table).unwrap();
+    assert!(table.starts_with("setpoint;points;dwell_min;reason\n6;2;40;few points\n"));
+    assert!(!DataFrame::is_table_header(table.lines().next().unwrap()));
+    assert_eq!(
+        Plan::read_setpoints(table.as_bytes()).unwrap(),
+        vec![(6, 2), (12, 3), (18, 3)]
+    );
+    assert!(Plan::read_setpoints("temp;dx;dy\r\n0;0;0".as_bytes()).is_err());
+    assert!(plan.to_string().ends_with("total: 2h 22min\r\n"));
+
+    // строка без смещения по x не разбирается, а в собранной вручную таблице не учитывается
+    assert!("temp;dx;dy\r\n# plus;;\r\n10;;5\r\n12;1;2"
+        .parse::<DataFrame>()
+        .is_err());
+    let raw = DataFrame {
+        rows: vec![
+            super::DataRow {
+                temp: Some(6),
+                x: None,
+                y: Some(5),
+            },
+            super::DataRow {
+                temp: Some(6),
+                x: Some(1),
+                y: Some(2),
+            },
+        ],
+        ..Default::default()
+    };
+    let plan = Plan::new(&raw, &calc, &PlanOptions::default());
+    assert_eq!(plan.setpoints[1].spread, 3);
+}
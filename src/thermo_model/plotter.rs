@@ -1,17 +1,29 @@
 use plotters::{
     coord::Shift,
     prelude::*,
-    style::full_palette::{DEEPORANGE, LIGHTBLUE_600, TEAL_400},
+    style::full_palette::{
+        AMBER_700, BROWN, DEEPORANGE, DEEPPURPLE_400, GREEN_600, GREY, INDIGO, LIGHTBLUE_600,
+        LIME_800, PINK_300, TEAL_400,
+    },
 };
 use std::cmp::{max, min};
 use std::error::Error;
+use std::path::Path;
+
+use super::firmware::Switching;
+use super::frame::{Direction, ResidualStats};
 
 const RESOLUTION: (u32, u32) = (1800, 1100);
+/// разрешение, для которого подобраны размеры шрифтов, линий и маркеров
+const BASE_DPI: u32 = 96;
 
 const MAIN_HEADER_SIZE: u32 = 40;
+const LABEL_AREA_SIZE: u32 = 40;
 const FONT: &str = "Microsoft Sans Serif";
 const X_HEADER: &str = "ГН";
 const Y_HEADER: &str = "ВН";
+/// шаг сетки температур, если его нельзя определить по модели
+const DEFAULT_GRID_STEP: i32 = 6;
 
 const CENTER_LINE_STYLE: ShapeStyle = ShapeStyle {
     color: RGBAColor(BLACK.0, BLACK.1, BLACK.2, 0.8),
@@ -35,34 +47,556 @@ const CALC_MARK_STYLE: ShapeStyle = ShapeStyle {
     filled: true,
     stroke_width: 1,
 };
+const OUT_OF_TOLERANCE_MARK_STYLE: ShapeStyle = ShapeStyle {
+    color: RGBAColor(RED.0, RED.1, RED.2, 1.0),
+    filled: true,
+    stroke_width: 1,
+};
+/// рамка вокруг исправленного вручную узла модели
+const OVERRIDE_MARK_STYLE: ShapeStyle = ShapeStyle {
+    color: RGBAColor(RED.0, RED.1, RED.2, 1.0),
+    filled: false,
+    stroke_width: 2,
+};
+const TOLERANCE_BAND_STYLE: ShapeStyle = ShapeStyle {
+    color: RGBAColor(GREEN.0, GREEN.1, GREEN.2, 0.15),
+    filled: true,
+    stroke_width: 0,
+};
+
+/// цвета сегментов измерений, первый совпадает с цветом сырых данных
+const SEGMENT_COLORS: [RGBColor; 9] = [
+    LIGHTBLUE_600,
+    DEEPPURPLE_400,
+    GREEN_600,
+    AMBER_700,
+    PINK_300,
+    BROWN,
+    INDIGO,
+    LIME_800,
+    BLACK,
+];
+
+const CURVE_LINE_STYLE: ShapeStyle = ShapeStyle {
+    color: RGBAColor(GREY.0, GREY.1, GREY.2, 0.6),
+    filled: false,
+    stroke_width: 1,
+};
+const ENVELOPE_STYLE: ShapeStyle = ShapeStyle {
+    color: RGBAColor(LIGHTBLUE_600.0, LIGHTBLUE_600.1, LIGHTBLUE_600.2, 0.2),
+    filled: true,
+    stroke_width: 0,
+};
+
+/// доля высоты области оси, занимаемая графиком отклонений
+const RESIDUAL_PANEL_RATIO: f64 = 0.35;
+
+/// Формат выходного файла с графиком
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Svg,
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Format::Png => write!(f, "png"),
+            Format::Svg => write!(f, "svg"),
+        }
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "png" => Ok(Format::Png),
+            "svg" => Ok(Format::Svg),
+            _ => Err(format!("Unsupported plot format: `{s}`"))?,
+        }
+    }
+}
+
+impl Format {
+    /// определение формата по расширению файла
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+/// Параметры вывода графика
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotOptions {
+    /// формат файла, если не задан, то берётся из расширения, иначе png
+    pub format: Option<Format>,
+    pub resolution: (u32, u32),
+    pub dpi: u32,
+    /// выводить панели с отклонениями от модели
+    pub residuals: bool,
+    /// допуск на отклонение от модели
+    pub tolerance: i32,
+    /// правило переключения ступеней таблицы, как в изделии
+    pub switching: Switching,
+    pub font: String,
+    /// названия осей, выводятся в заголовках графиков
+    pub axis_names: (String, String),
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        Self {
+            format: None,
+            resolution: RESOLUTION,
+            dpi: BASE_DPI,
+            residuals: false,
+            tolerance: 1,
+            switching: Switching::default(),
+            font: FONT.to_string(),
+            axis_names: (X_HEADER.to_string(), Y_HEADER.to_string()),
+        }
+    }
+}
+
+impl PlotOptions {
+    /// итоговый формат для файла `path`
+    pub fn format_for(&self, path: &Path) -> Format {
+        self.format
+            .or_else(|| Format::from_path(path))
+            .unwrap_or(Format::Png)
+    }
+
+    /// коэффициент масштабирования элементов графика
+    fn scale(&self) -> f64 {
+        self.dpi as f64 / BASE_DPI as f64
+    }
+}
+
+/// разбор разрешения в формате `ШИРИНАxВЫСОТА`
+pub fn parse_resolution(s: &str) -> Result<(u32, u32), Box<dyn Error>> {
+    let (w, h) = s
+        .split_once(['x', 'X', '×'])
+        .ok_or(format!("Invalid resolution `{s}`, expected WIDTHxHEIGHT"))?;
+    let (w, h) = (w.trim().parse()?, h.trim().parse()?);
+    if w == 0 || h == 0 {
+        Err(format!(
+            "Invalid resolution `{s}`, width and height must be positive"
+        ))?
+    }
+    Ok((w, h))
+}
+
+/// Данные для графика одной оси
+#[derive(Default, Debug, Clone)]
+pub struct Panel {
+    pub raw: Vec<(i32, i32)>,
+    pub calc: Vec<(i32, i32)>,
+    /// отклонения сырых данных от модели, выводятся отдельной панелью
+    pub residuals: Option<Vec<(i32, i32)>>,
+    /// сырые данные по сегментам, если заданы, то точки раскрашиваются
+    /// по сегментам и выводится легенда
+    pub segments: Vec<SegmentPoints>,
+    /// исправленные вручную узлы модели с подписью для легенды
+    pub overrides: Vec<(String, (i32, i32))>,
+}
+
+/// Сырые данные одного сегмента измерений
+#[derive(Debug, Clone)]
+pub struct SegmentPoints {
+    pub label: String,
+    pub direction: Direction,
+    pub points: Vec<(i32, i32)>,
+}
 
 pub fn plot(
-    path: &str,
+    path: &Path,
+    options: &PlotOptions,
     header: &str,
-    raw_data_x: Vec<(i32, i32)>,
-    calc_data_x: Vec<(i32, i32)>,
-    raw_data_y: Vec<(i32, i32)>,
-    calc_data_y: Vec<(i32, i32)>,
+    panel_x: Panel,
+    panel_y: Panel,
 ) -> Result<(), Box<dyn Error>> {
-    let canvas = BitMapBackend::new(path, RESOLUTION).into_drawing_area();
+    match options.format_for(path) {
+        Format::Png => draw(
+            BitMapBackend::new(path, options.resolution).into_drawing_area(),
+            options,
+            header,
+            panel_x,
+            panel_y,
+        ),
+        Format::Svg => draw(
+            SVGBackend::new(path, options.resolution).into_drawing_area(),
+            options,
+            header,
+            panel_x,
+            panel_y,
+        ),
+    }
+}
+
+/// построение графика в формате svg в памяти
+pub fn plot_svg(
+    options: &PlotOptions,
+    header: &str,
+    panel_x: Panel,
+    panel_y: Panel,
+) -> Result<String, Box<dyn Error>> {
+    let mut svg = String::new();
+    draw(
+        SVGBackend::with_string(&mut svg, options.resolution).into_drawing_area(),
+        options,
+        header,
+        panel_x,
+        panel_y,
+    )?;
+    Ok(svg)
+}
+
+/// Наложенные кривые нескольких моделей одной оси
+#[derive(Default, Debug, Clone)]
+pub struct Overlay {
+    pub curves: Vec<Curve>,
+    /// полоса разброса: температура, нижняя и верхняя граница
+    pub band: Vec<(i32, f64, f64)>,
+    pub band_label: String,
+    /// средняя кривая
+    pub mean: Vec<(i32, f64)>,
+}
+
+/// Кривая одной модели
+#[derive(Debug, Clone)]
+pub struct Curve {
+    pub label: String,
+    pub points: Vec<(i32, i32)>,
+    /// выделенные кривые выводятся цветом и подписываются в легенде
+    pub highlighted: bool,
+}
+
+/// график с наложенными кривыми нескольких моделей
+pub fn plot_overlay(
+    path: &Path,
+    options: &PlotOptions,
+    header: &str,
+    overlay_x: Overlay,
+    overlay_y: Overlay,
+) -> Result<(), Box<dyn Error>> {
+    match options.format_for(path) {
+        Format::Png => draw_overlay(
+            BitMapBackend::new(path, options.resolution).into_drawing_area(),
+            options,
+            header,
+            overlay_x,
+            overlay_y,
+        ),
+        Format::Svg => draw_overlay(
+            SVGBackend::new(path, options.resolution).into_drawing_area(),
+            options,
+            header,
+            overlay_x,
+            overlay_y,
+        ),
+    }
+}
+
+/// Ряд значений во времени: минуты и значение
+pub type Series = Vec<(f64, f64)>;
+
+/// график во времени: температура и ошибки по осям x и y с полосой допуска
+pub fn plot_timeline(
+    path: &Path,
+    options: &PlotOptions,
+    header: &str,
+    temps: Series,
+    errors: (Series, Series),
+) -> Result<(), Box<dyn Error>> {
+    match options.format_for(path) {
+        Format::Png => draw_timeline(
+            BitMapBackend::new(path, options.resolution).into_drawing_area(),
+            options,
+            header,
+            temps,
+            errors,
+        ),
+        Format::Svg => draw_timeline(
+            SVGBackend::new(path, options.resolution).into_drawing_area(),
+            options,
+            header,
+            temps,
+            errors,
+        ),
+    }
+}
+
+fn draw<DB: DrawingBackend>(
+    canvas: DrawingArea<DB, Shift>,
+    options: &PlotOptions,
+    header: &str,
+    panel_x: Panel,
+    panel_y: Panel,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let header_size = scaled(MAIN_HEADER_SIZE, options.scale());
+
+    canvas.fill(&WHITE)?;
+    let canvas = canvas.titled(header, (options.font.as_str(), header_size))?;
+
+    let (upper, lower) =
+        canvas.split_vertically((options.resolution.1.saturating_sub(header_size)) / 2);
+
+    plot_panel(upper, options, &options.axis_names.0, panel_x)?;
+    plot_panel(lower, options, &options.axis_names.1, panel_y)?;
+
+    canvas.present()?;
+    Ok(())
+}
+
+fn draw_overlay<DB: DrawingBackend>(
+    canvas: DrawingArea<DB, Shift>,
+    options: &PlotOptions,
+    header: &str,
+    overlay_x: Overlay,
+    overlay_y: Overlay,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let header_size = scaled(MAIN_HEADER_SIZE, options.scale());
+
     canvas.fill(&WHITE)?;
-    let canvas = canvas.titled(header, (FONT, MAIN_HEADER_SIZE))?;
+    let canvas = canvas.titled(header, (options.font.as_str(), header_size))?;
 
-    let (upper, lower) = canvas.split_vertically((RESOLUTION.1 - MAIN_HEADER_SIZE) / 2);
+    let (upper, lower) =
+        canvas.split_vertically((options.resolution.1.saturating_sub(header_size)) / 2);
 
-    plot_area(upper, X_HEADER, raw_data_x, calc_data_x)?;
-    plot_area(lower, Y_HEADER, raw_data_y, calc_data_y)?;
+    plot_overlay_area(upper, options, &options.axis_names.0, overlay_x)?;
+    plot_overlay_area(lower, options, &options.axis_names.1, overlay_y)?;
 
     canvas.present()?;
     Ok(())
 }
 
-fn plot_area(
-    area: DrawingArea<BitMapBackend, Shift>,
+fn draw_timeline<DB: DrawingBackend>(
+    canvas: DrawingArea<DB, Shift>,
+    options: &PlotOptions,
+    header: &str,
+    temps: Series,
+    errors: (Series, Series),
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let header_size = scaled(MAIN_HEADER_SIZE, options.scale());
+
+    canvas.fill(&WHITE)?;
+    let canvas = canvas.titled(header, (options.font.as_str(), header_size))?;
+
+    let panels = canvas.split_evenly((3, 1));
+    plot_timeline_area(panels[0].clone(), options, "°C", temps, None)?;
+    plot_timeline_area(
+        panels[1].clone(),
+        options,
+        &options.axis_names.0,
+        errors.0,
+        Some(options.tolerance.abs() as f64),
+    )?;
+    plot_timeline_area(
+        panels[2].clone(),
+        options,
+        &options.axis_names.1,
+        errors.1,
+        Some(options.tolerance.abs() as f64),
+    )?;
+
+    canvas.present()?;
+    Ok(())
+}
+
+/// одна панель графика во времени, для ошибок с полосой допуска `tolerance`
+fn plot_timeline_area<DB: DrawingBackend>(
+    area: DrawingArea<DB, Shift>,
+    options: &PlotOptions,
+    header: &str,
+    series: Series,
+    tolerance: Option<f64>,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let scale = options.scale();
+    let font = options.font.as_str();
+    let font_size = scaled(MAIN_HEADER_SIZE / 2, scale);
+    let label_area_size = scaled(LABEL_AREA_SIZE, scale);
+
+    let times = series.iter().map(|(time, _)| *time);
+    let (start, end) = (
+        times.clone().fold(f64::INFINITY, f64::min),
+        times.fold(f64::NEG_INFINITY, f64::max),
+    );
+    let (start, end) = if start < end {
+        (start, end)
+    } else {
+        (0.0, 1.0)
+    };
+    let values = series
+        .iter()
+        .map(|(_, value)| *value)
+        .chain(tolerance.into_iter().flat_map(|band| [-band, band]));
+    let y_min = values.clone().fold(0.0, f64::min).floor() - 1.0;
+    let y_max = values.fold(0.0, f64::max).ceil() + 1.0;
+
+    let mut chart = ChartBuilder::on(&area)
+        .caption(header, (font, font_size))
+        .set_label_area_size(LabelAreaPosition::Left, label_area_size)
+        .set_label_area_size(LabelAreaPosition::Right, label_area_size)
+        .set_label_area_size(LabelAreaPosition::Bottom, label_area_size)
+        .build_cartesian_2d(start..end, y_min..y_max)?;
+
+    chart
+        .configure_mesh()
+        .label_style((font, font_size))
+        .x_label_formatter(&|minutes| {
+            let minutes = minutes.round() as i64;
+            format!("{}:{:02}", minutes.div_euclid(60), minutes.rem_euclid(60))
+        })
+        // при малом размахе деления дробные, `+ 0.0` убирает минус у нуля
+        .y_label_formatter(&|y| format!("{}", (y * 10.0).round() / 10.0 + 0.0))
+        .draw()?;
+
+    if let Some(band) = tolerance {
+        chart.draw_series(std::iter::once(Rectangle::new(
+            [(start, -band), (end, band)],
+            TOLERANCE_BAND_STYLE,
+        )))?;
+        chart.draw_series(LineSeries::new(
+            [(start, 0.0), (end, 0.0)],
+            scaled_style(CENTER_LINE_STYLE, scale),
+        ))?;
+    }
+
+    chart.draw_series(LineSeries::new(
+        series.iter().copied(),
+        scaled_style(STEPPED_LINE_STYLE, scale),
+    ))?;
+    if let Some(band) = tolerance {
+        chart.draw_series(
+            series
+                .iter()
+                .filter(|(_, value)| value.abs() > band)
+                .map(|coord| {
+                    Circle::new(
+                        *coord,
+                        scaled(MARK_SIZE, scale) / 2,
+                        scaled_style(OUT_OF_TOLERANCE_MARK_STYLE, scale),
+                    )
+                }),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn scaled(size: u32, scale: f64) -> u32 {
+    max(1, (size as f64 * scale).round() as u32)
+}
+
+fn scaled_style(style: ShapeStyle, scale: f64) -> ShapeStyle {
+    ShapeStyle {
+        stroke_width: scaled(style.stroke_width, scale),
+        ..style
+    }
+}
+
+fn plot_panel<DB: DrawingBackend>(
+    area: DrawingArea<DB, Shift>,
+    options: &PlotOptions,
+    header: &str,
+    panel: Panel,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let x_axis = XAxis::new(&panel.calc);
+
+    match panel.residuals {
+        Some(residuals) => {
+            let height = area.dim_in_pixel().1 as f64;
+            let (upper, lower) =
+                area.split_vertically((height * (1.0 - RESIDUAL_PANEL_RATIO)).round() as u32);
+
+            plot_area(
+                upper,
+                options,
+                &x_axis,
+                header,
+                panel.raw,
+                panel.calc,
+                panel.segments,
+                panel.overrides,
+            )?;
+            plot_residuals(lower, options, &x_axis, residuals)
+        }
+        None => plot_area(
+            area,
+            options,
+            &x_axis,
+            header,
+            panel.raw,
+            panel.calc,
+            panel.segments,
+            panel.overrides,
+        ),
+    }
+}
+
+/// Ось температур: узлы модели с запасом в один шаг с каждой стороны
+struct XAxis {
+    start: i32,
+    end: i32,
+    step: i32,
+    labels: usize,
+}
+
+impl XAxis {
+    fn new(calc_data: &[(i32, i32)]) -> Self {
+        let step = match calc_data {
+            [first, second, ..] if second.0 > first.0 => second.0 - first.0,
+            _ => DEFAULT_GRID_STEP,
+        };
+        let first = calc_data.first().map_or(-50, |(temp, _)| *temp);
+        let last = calc_data.last().map_or(70, |(temp, _)| *temp);
+
+        Self {
+            start: first - step,
+            end: last + step,
+            step,
+            labels: max(calc_data.len(), 2),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn plot_area<DB: DrawingBackend>(
+    area: DrawingArea<DB, Shift>,
+    options: &PlotOptions,
+    x_axis: &XAxis,
     header: &str,
     raw_data: Vec<(i32, i32)>,
     calc_data: Vec<(i32, i32)>,
-) -> Result<(), Box<dyn Error>> {
+    segments: Vec<SegmentPoints>,
+    overrides: Vec<(String, (i32, i32))>,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let scale = options.scale();
+    let font = options.font.as_str();
+    let font_size = scaled(MAIN_HEADER_SIZE / 2, scale);
+    let label_area_size = scaled(LABEL_AREA_SIZE, scale);
+    let mark_size = scaled(MARK_SIZE, scale);
+    let stepped_line_style = scaled_style(STEPPED_LINE_STYLE, scale);
+
     let y_min = min(
         raw_data.iter().map(|(_, y)| y).min().unwrap_or(&0),
         calc_data.iter().map(|(_, y)| y).min().unwrap_or(&0),
@@ -73,19 +607,19 @@ fn plot_area(
     ) + 1;
 
     let mut chart = ChartBuilder::on(&area)
-        .caption(header, (FONT, MAIN_HEADER_SIZE / 2))
-        .set_label_area_size(LabelAreaPosition::Left, 40)
-        .set_label_area_size(LabelAreaPosition::Right, 40)
-        .set_label_area_size(LabelAreaPosition::Bottom, 40)
-        .build_cartesian_2d((-56..76).step(6), y_min..y_max)?;
+        .caption(header, (font, font_size))
+        .set_label_area_size(LabelAreaPosition::Left, label_area_size)
+        .set_label_area_size(LabelAreaPosition::Right, label_area_size)
+        .set_label_area_size(LabelAreaPosition::Bottom, label_area_size)
+        .build_cartesian_2d((x_axis.start..x_axis.end).step(x_axis.step), y_min..y_max)?;
 
     chart
         .configure_mesh()
-        .x_labels(21)
+        .x_labels(x_axis.labels)
         .y_labels((y_max.abs() + y_min.abs()) as usize)
-        .label_style((FONT, MAIN_HEADER_SIZE / 2))
+        .label_style((font, font_size))
         .x_label_formatter(&|x| {
-            if *x == -56 {
+            if *x == x_axis.start {
                 String::default()
             } else {
                 format!("{}", x)
@@ -94,47 +628,372 @@ fn plot_area(
         .draw()?;
 
     chart.draw_series(LineSeries::new(
-        (-56..=76).map(|x| (x, 0)),
-        CENTER_LINE_STYLE,
+        (x_axis.start..=x_axis.end).map(|x| (x, 0)),
+        scaled_style(CENTER_LINE_STYLE, scale),
     ))?;
 
-    chart.draw_series(
-        raw_data
-            .iter()
-            .map(|coord| Circle::new(*coord, MARK_SIZE, RAW_MARK_STYLE)),
-    )?;
+    if segments.is_empty() {
+        chart.draw_series(
+            raw_data
+                .iter()
+                .map(|coord| Circle::new(*coord, mark_size, scaled_style(RAW_MARK_STYLE, scale))),
+        )?;
+    }
 
-    chart.draw_series(
-        calc_data
-            .iter()
-            .map(|coord| TriangleMarker::new(*coord, MARK_SIZE, CALC_MARK_STYLE)),
-    )?;
+    for (i, segment) in segments.iter().enumerate() {
+        let color = SEGMENT_COLORS[i % SEGMENT_COLORS.len()];
+        let style = scaled_style(
+            ShapeStyle {
+                color: color.to_rgba(),
+                ..RAW_MARK_STYLE
+            },
+            scale,
+        );
+        let label = if segment.label.is_empty() {
+            format!("#{}", i + 1)
+        } else {
+            segment.label.clone()
+        };
 
+        match segment.direction {
+            Direction::Plus => chart
+                .draw_series(
+                    segment
+                        .points
+                        .iter()
+                        .map(|coord| Circle::new(*coord, mark_size, style)),
+                )?
+                .label(label)
+                .legend(move |coord| Circle::new(coord, mark_size, style)),
+            Direction::Minus => {
+                let style = style.stroke_width(scaled(2, scale));
+                chart
+                    .draw_series(
+                        segment
+                            .points
+                            .iter()
+                            .map(|coord| Cross::new(*coord, mark_size, style)),
+                    )?
+                    .label(label)
+                    .legend(move |coord| Cross::new(coord, mark_size, style))
+            }
+            Direction::Unknown => {
+                let style = ShapeStyle {
+                    filled: false,
+                    ..style
+                };
+                chart
+                    .draw_series(
+                        segment
+                            .points
+                            .iter()
+                            .map(|coord| Circle::new(*coord, mark_size, style)),
+                    )?
+                    .label(label)
+                    .legend(move |coord| Circle::new(coord, mark_size, style))
+            }
+        };
+    }
+
+    chart.draw_series(calc_data.iter().map(|coord| {
+        TriangleMarker::new(*coord, mark_size, scaled_style(CALC_MARK_STYLE, scale))
+    }))?;
+
+    // ступени переключаются по правилу изделия, при гистерезисе в зоне действуют обе ступени
     for pairs in calc_data.windows(2) {
-        if pairs[0].1 == pairs[1].1 {
-            chart.draw_series(LineSeries::new(
-                (pairs[0].0..=pairs[1].0).map(|_x| (_x, pairs[0].1)),
-                STEPPED_LINE_STYLE,
-            ))?;
-        } else {
-            let average = (pairs[0].0 + pairs[1].0) / 2;
-
-            chart.draw_series(LineSeries::new(
-                (pairs[0].0..=average).map(|_x| (_x, pairs[0].1)),
-                STEPPED_LINE_STYLE,
-            ))?;
-
-            chart.draw_series(LineSeries::new(
-                (min(pairs[0].1, pairs[1].1)..=max(pairs[0].1, pairs[1].1)).map(|_y| (average, _y)),
-                STEPPED_LINE_STYLE,
-            ))?;
-
-            chart.draw_series(LineSeries::new(
-                (average..=pairs[1].0).map(|_x| (_x, pairs[1].1)),
-                STEPPED_LINE_STYLE,
-            ))?;
+        let (down, up) = options.switching.thresholds(pairs[0].0, pairs[1].0);
+        chart.draw_series(LineSeries::new(
+            (pairs[0].0..=up).map(|_x| (_x, pairs[0].1)),
+            stepped_line_style,
+        ))?;
+        chart.draw_series(LineSeries::new(
+            (down..=pairs[1].0).map(|_x| (_x, pairs[1].1)),
+            stepped_line_style,
+        ))?;
+
+        if pairs[0].1 != pairs[1].1 {
+            for switch in [up, down] {
+                chart.draw_series(LineSeries::new(
+                    (min(pairs[0].1, pairs[1].1)..=max(pairs[0].1, pairs[1].1))
+                        .map(|_y| (switch, _y)),
+                    stepped_line_style,
+                ))?;
+            }
         }
     }
 
+    let override_style = scaled_style(OVERRIDE_MARK_STYLE, scale);
+    for (label, coord) in overrides.iter() {
+        let size = 2 * mark_size as i32;
+        chart
+            .draw_series([EmptyElement::at(*coord)
+                + Rectangle::new([(-size, -size), (size, size)], override_style)])?
+            .label(format!("override {label}"))
+            .legend(move |(x, y)| {
+                Rectangle::new(
+                    [(x - size / 2, y - size / 2), (x + size / 2, y + size / 2)],
+                    override_style,
+                )
+            });
+    }
+
+    if !segments.is_empty() || !overrides.is_empty() {
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK.mix(0.5))
+            .label_font((font, font_size))
+            .draw()?;
+    }
+
+    Ok(())
+}
+
+fn plot_overlay_area<DB: DrawingBackend>(
+    area: DrawingArea<DB, Shift>,
+    options: &PlotOptions,
+    header: &str,
+    overlay: Overlay,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let scale = options.scale();
+    let font = options.font.as_str();
+    let font_size = scaled(MAIN_HEADER_SIZE / 2, scale);
+    let label_area_size = scaled(LABEL_AREA_SIZE, scale);
+    let mark_size = scaled(MARK_SIZE, scale);
+
+    let mut temps: Vec<(i32, i32)> = overlay
+        .curves
+        .iter()
+        .flat_map(|curve| curve.points.iter().map(|(temp, _)| (*temp, 0)))
+        .chain(overlay.band.iter().map(|(temp, _, _)| (*temp, 0)))
+        .collect();
+    temps.sort();
+    temps.dedup();
+    let x_axis = XAxis::new(&temps);
+
+    let values: Vec<f64> = overlay
+        .curves
+        .iter()
+        .flat_map(|curve| curve.points.iter().map(|(_, y)| *y as f64))
+        .chain(overlay.band.iter().flat_map(|(_, low, high)| [*low, *high]))
+        .chain(overlay.mean.iter().map(|(_, y)| *y))
+        .collect();
+    let y_min = values.iter().copied().fold(0.0, f64::min).floor() - 1.0;
+    let y_max = values.iter().copied().fold(0.0, f64::max).ceil() + 1.0;
+
+    let mut chart = ChartBuilder::on(&area)
+        .caption(header, (font, font_size))
+        .set_label_area_size(LabelAreaPosition::Left, label_area_size)
+        .set_label_area_size(LabelAreaPosition::Right, label_area_size)
+        .set_label_area_size(LabelAreaPosition::Bottom, label_area_size)
+        .build_cartesian_2d((x_axis.start..x_axis.end).step(x_axis.step), y_min..y_max)?;
+
+    chart
+        .configure_mesh()
+        .x_labels(x_axis.labels)
+        .y_labels((y_max - y_min) as usize)
+        .y_label_formatter(&|y| format!("{y:.0}"))
+        .label_style((font, font_size))
+        .x_label_formatter(&|x| {
+            if *x == x_axis.start {
+                String::default()
+            } else {
+                format!("{}", x)
+            }
+        })
+        .draw()?;
+
+    if !overlay.band.is_empty() {
+        let outline = overlay
+            .band
+            .iter()
+            .map(|(temp, _, high)| (*temp, *high))
+            .chain(
+                overlay
+                    .band
+                    .iter()
+                    .rev()
+                    .map(|(temp, low, _)| (*temp, *low)),
+            );
+        chart
+            .draw_series(std::iter::once(Polygon::new(
+                outline.collect::<Vec<_>>(),
+                ENVELOPE_STYLE,
+            )))?
+            .label(overlay.band_label.as_str())
+            .legend(move |(x, y)| {
+                Rectangle::new(
+                    [
+                        (x, y - mark_size as i32),
+                        (x + 2 * mark_size as i32, y + mark_size as i32),
+                    ],
+                    ENVELOPE_STYLE,
+                )
+            });
+    }
+
+    chart.draw_series(LineSeries::new(
+        (x_axis.start..=x_axis.end).map(|x| (x, 0.0)),
+        scaled_style(CENTER_LINE_STYLE, scale),
+    ))?;
+
+    for curve in overlay.curves.iter().filter(|curve| !curve.highlighted) {
+        chart.draw_series(LineSeries::new(
+            curve.points.iter().map(|(x, y)| (*x, *y as f64)),
+            scaled_style(CURVE_LINE_STYLE, scale),
+        ))?;
+    }
+
+    if !overlay.mean.is_empty() {
+        let style = scaled_style(STEPPED_LINE_STYLE, scale);
+        chart
+            .draw_series(LineSeries::new(overlay.mean.iter().copied(), style))?
+            .label("mean")
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 2 * mark_size as i32, y)], style));
+    }
+
+    for (i, curve) in overlay
+        .curves
+        .iter()
+        .filter(|curve| curve.highlighted)
+        .enumerate()
+    {
+        let color = SEGMENT_COLORS[(i + 1) % SEGMENT_COLORS.len()];
+        let style = scaled_style(
+            ShapeStyle {
+                color: color.to_rgba(),
+                filled: true,
+                stroke_width: 2,
+            },
+            scale,
+        );
+        chart.draw_series(
+            curve
+                .points
+                .iter()
+                .map(|(x, y)| Circle::new((*x, *y as f64), mark_size, style)),
+        )?;
+        chart
+            .draw_series(LineSeries::new(
+                curve.points.iter().map(|(x, y)| (*x, *y as f64)),
+                style,
+            ))?
+            .label(curve.label.as_str())
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 2 * mark_size as i32, y)], style));
+    }
+
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperRight)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK.mix(0.5))
+        .label_font((font, font_size))
+        .draw()?;
+
     Ok(())
 }
+
+fn plot_residuals<DB: DrawingBackend>(
+    area: DrawingArea<DB, Shift>,
+    options: &PlotOptions,
+    x_axis: &XAxis,
+    residuals: Vec<(i32, i32)>,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let scale = options.scale();
+    let font = options.font.as_str();
+    let font_size = scaled(MAIN_HEADER_SIZE / 2, scale);
+    let label_area_size = scaled(LABEL_AREA_SIZE, scale);
+    let mark_size = scaled(MARK_SIZE, scale);
+    let tolerance = options.tolerance.abs();
+
+    let limit = residuals
+        .iter()
+        .map(|(_, r)| r.abs())
+        .max()
+        .unwrap_or(0)
+        .max(tolerance)
+        + 1;
+
+    let mut chart = ChartBuilder::on(&area)
+        .set_label_area_size(LabelAreaPosition::Left, label_area_size)
+        .set_label_area_size(LabelAreaPosition::Right, label_area_size)
+        .set_label_area_size(LabelAreaPosition::Bottom, label_area_size)
+        .build_cartesian_2d((x_axis.start..x_axis.end).step(x_axis.step), -limit..limit)?;
+
+    chart
+        .configure_mesh()
+        .x_labels(x_axis.labels)
+        .y_labels((2 * limit) as usize)
+        .label_style((font, font_size))
+        .x_label_formatter(&|x| {
+            if *x == x_axis.start {
+                String::default()
+            } else {
+                format!("{}", x)
+            }
+        })
+        .draw()?;
+
+    chart.draw_series(std::iter::once(Rectangle::new(
+        [(x_axis.start, -tolerance), (x_axis.end, tolerance)],
+        TOLERANCE_BAND_STYLE,
+    )))?;
+
+    chart.draw_series(LineSeries::new(
+        (x_axis.start..=x_axis.end).map(|x| (x, 0)),
+        scaled_style(CENTER_LINE_STYLE, scale),
+    ))?;
+
+    chart.draw_series(residuals.iter().map(|coord| {
+        let style = if coord.1.abs() > tolerance {
+            OUT_OF_TOLERANCE_MARK_STYLE
+        } else {
+            RAW_MARK_STYLE
+        };
+        Circle::new(*coord, mark_size, scaled_style(style, scale))
+    }))?;
+
+    let stats = ResidualStats::new(
+        &residuals.iter().map(|(_, r)| *r).collect::<Vec<i32>>(),
+        tolerance,
+    );
+    area.draw(&Text::new(
+        format!("±{tolerance}: {stats}"),
+        (2 * label_area_size as i32, font_size as i32 / 2),
+        (font, font_size),
+    ))?;
+
+    Ok(())
+}
+
+#[test]
+fn plot_format() {
+    assert_eq!(
+        Format::from_path(Path::new("a/b.c/plot.svg")),
+        Some(Format::Svg)
+    );
+    assert_eq!(Format::from_path(Path::new("plot.PNG")), Some(Format::Png));
+    assert_eq!(Format::from_path(Path::new("plot")), None);
+
+    let options = PlotOptions {
+        format: Some(Format::Svg),
+        ..Default::default()
+    };
+    assert_eq!(options.format_for(Path::new("plot.png")), Format::Svg);
+    assert_eq!(
+        PlotOptions::default().format_for(Path::new("plot")),
+        Format::Png
+    );
+
+    assert_eq!(parse_resolution("1920x1080").unwrap(), (1920, 1080));
+    assert!(parse_resolution("1920").is_err());
+    assert!(parse_resolution("0x0").is_err());
+    assert!(parse_resolution("1920x0").is_err());
+}
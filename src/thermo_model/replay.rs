@@ -0,0 +1,277 @@
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+
+use super::plotter::{self, PlotOptions};
+use super::{CalcOptions, Constraints, DataFrame, Firmware, Switching};
+
+/// Момент температурного профиля
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayPoint {
+    /// время от начала профиля, мин
+    pub time: f64,
+    pub temp: f64,
+    /// коррекция из таблицы изделия
+    pub correction: (i32, i32),
+    /// уход по сырым данным
+    pub drift: (f64, f64),
+}
+
+impl ReplayPoint {
+    /// остаточная ошибка наведения: уход, не скомпенсированный таблицей
+    pub fn error(&self) -> (f64, f64) {
+        (
+            self.drift.0 - self.correction.0 as f64,
+            self.drift.1 - self.correction.1 as f64,
+        )
+    }
+}
+
+/// Статистика ошибки одной оси по времени
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct ErrorStats {
+    pub max_abs: f64,
+    pub rms: f64,
+    /// доля времени в пределах допуска, %
+    pub within: f64,
+}
+
+impl std::fmt::Display for ErrorStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "max = {:.2}, rms = {:.2}, in tolerance = {:.1}% of time",
+            self.max_abs, self.rms, self.within
+        )
+    }
+}
+
+/// Остаточная ошибка наведения на температурном профиле при работе изделия с таблицей
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub points: Vec<ReplayPoint>,
+    /// диапазон температур сырых данных, за ним уход считается постоянным
+    pub measured: (i32, i32),
+    pub switching: Switching,
+}
+
+impl Replay {
+    /// проход профиля `(время, температура)` через таблицу `table` по правилу `switching`;
+    /// уход при каждой температуре оценивается по сырым данным `raw` в каждом градусе
+    /// с окном и способом оценки из `calc`
+    pub fn new(
+        raw: &DataFrame,
+        table: &DataFrame,
+        profile: &[(f64, f64)],
+        calc: &CalcOptions,
+        switching: Switching,
+    ) -> Result<Self, Box<dyn Error>> {
+        let temps: Vec<i32> = raw.rows.iter().filter_map(|row| row.temp).collect();
+        let (Some(first), Some(last)) = (temps.iter().min(), temps.iter().max()) else {
+            Err("No raw data")?
+        };
+        let drift: Vec<(i32, (i32, i32))> = raw
+            .calc(&CalcOptions {
+                grid_start: *first,
+                grid_end: *last,
+                grid_step: 1,
+                // уход оценивается по данным, а не по ограниченной таблице
+                constraints: Constraints::default(),
+                ..*calc
+            })
+            .rows
+            .iter()
+            .filter_map(|row| Some((row.temp?, (row.x?, row.y?))))
+            .collect();
+
+        let mut firmware = Firmware::new(table, switching)?;
+        let points = profile
+            .iter()
+            .map(|(time, temp)| ReplayPoint {
+                time: *time,
+                temp: *temp,
+                correction: firmware.lookup(*temp),
+                drift: interpolate(&drift, *temp),
+            })
+            .collect();
+        Ok(Self {
+            points,
+            measured: (*first, *last),
+            switching,
+        })
+    }
+
+    /// веса точек по времени: каждой точке принадлежат половины интервалов до соседних
+    fn weights(&self) -> Vec<f64> {
+        let count = self.points.len();
+        let time = |i: usize| self.points[i.min(count - 1)].time;
+        let weights: Vec<f64> = (0..count)
+            .map(|i| ((time(i + 1) - time(i.saturating_sub(1))) / 2.0).max(0.0))
+            .collect();
+        if weights.iter().sum::<f64>() > 0.0 {
+            weights
+        } else {
+            vec![1.0; count]
+        }
+    }
+
+    /// доля времени, %, в которую выполняется условие `condition`
+    fn share<F: Fn(&ReplayPoint) -> bool>(&self, condition: F) -> f64 {
+        let weights = self.weights();
+        let part: f64 = self
+            .points
+            .iter()
+            .zip(weights.iter())
+            .filter(|(point, _)| condition(point))
+            .fold(0.0, |sum, (_, weight)| sum + weight);
+        100.0 * part / weights.iter().sum::<f64>().max(f64::MIN_POSITIVE)
+    }
+
+    /// статистика ошибки по осям x и y и доля времени в допуске по обеим осям, %
+    pub fn stats(&self, tolerance: i32) -> (ErrorStats, ErrorStats, f64) {
+        let tolerance = tolerance.abs() as f64;
+        let weights = self.weights();
+        let total: f64 = weights.iter().sum::<f64>().max(f64::MIN_POSITIVE);
+        let axis = |error: fn((f64, f64)) -> f64| ErrorStats {
+            max_abs: self
+                .points
+                .iter()
+                .map(|point| error(point.error()).abs())
+                .fold(0.0, f64::max),
+            rms: (self
+                .points
+                .iter()
+                .zip(weights.iter())
+                .map(|(point, weight)| error(point.error()).powi(2) * weight)
+                .sum::<f64>()
+                / total)
+                .sqrt(),
+            within: self.share(|point| error(point.error()).abs() <= tolerance),
+        };
+        let both = self.share(|point| {
+            let (x, y) = point.error();
+            x.abs() <= tolerance && y.abs() <= tolerance
+        });
+        (axis(|(x, _)| x), axis(|(_, y)| y), both)
+    }
+
+    /// доля времени вне диапазона температур сырых данных, %
+    pub fn outside(&self) -> f64 {
+        let (first, last) = (self.measured.0 as f64, self.measured.1 as f64);
+        self.share(|point| point.temp < first || point.temp > last)
+    }
+
+    /// запись строк `time;temp;dx;dy;error_x;error_y`
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), Box<dyn Error>> {
+        writeln!(writer, "time;temp;dx;dy;error_x;error_y")?;
+        for point in self.points.iter() {
+            let (error_x, error_y) = point.error();
+            writeln!(
+                writer,
+                "{};{};{};{};{error_x:.2};{error_y:.2}",
+                point.time, point.temp, point.correction.0, point.correction.1
+            )?;
+        }
+        Ok(())
+    }
+
+    /// график температуры и ошибок по осям во времени, в заголовке — статистика
+    pub fn plot(&self, path: &Path, options: &PlotOptions) -> Result<(), Box<dyn Error>> {
+        let (x, y, both) = self.stats(options.tolerance);
+        let header = format!(
+            "{}: max {:.2} / {:.2}, ±{} both axes {:.1}% of time",
+            self.switching, x.max_abs, y.max_abs, options.tolerance, both
+        );
+        let series = |value: fn(&ReplayPoint) -> f64| -> Vec<(f64, f64)> {
+            self.points
+                .iter()
+                .map(|point| (point.time, value(point)))
+                .collect()
+        };
+        plotter::plot_timeline(
+            path,
+            options,
+            &header,
+            series(|point| point.temp),
+            (
+                series(|point| point.error().0),
+                series(|point| point.error().1),
+            ),
+        )
+    }
+}
+
+/// линейная интерполяция значений в узлах `nodes`, за крайними узлами значение постоянно
+fn interpolate(nodes: &[(i32, (i32, i32))], temp: f64) -> (f64, f64) {
+    let value = |(x, y): (i32, i32)| (x as f64, y as f64);
+    let (Some(first), Some(last)) = (nodes.first(), nodes.last()) else {
+        return (0.0, 0.0);
+    };
+    if temp <= first.0 as f64 {
+        return value(first.1);
+    }
+    for pair in nodes.windows(2) {
+        let ((t0, v0), (t1, v1)) = (pair[0], pair[1]);
+        if temp <= t1 as f64 {
+            let k = (temp - t0 as f64) / (t1 - t0) as f64;
+            let ((x0, y0), (x1, y1)) = (value(v0), value(v1));
+            return (x0 + k * (x1 - x0), y0 + k * (y1 - y0));
+        }
+    }
+    value(last.1)
+}
+
+#[test]
+fn profile_replay() {
+    let raw: DataFrame = "temp;dx;dy\r\n0;0;0\r\n2;1;0\r\n4;1;0\r\n6;2;0\r\n8;2;0\r\n10;3;0"
+        .parse()
+        .unwrap();
+    let calc = CalcOptions {
+        grid_start: 0,
+        grid_end: 10,
+        grid_step: 5,
+        window: 0,
+        ..Default::default()
+    };
+    let table = raw.calc(&CalcOptions { window: 1, ..calc });
+    assert_eq!(interpolate(&[(0, (0, 0)), (2, (1, -2))], 1.0), (0.5, -1.0));
+
+    // нагрев за 10 минут, затем 15 минут из 30 при 20 °C вне диапазона измерений
+    let profile: Vec<(f64, f64)> = (0..=10)
+        .map(|minute| (minute as f64, minute as f64))
+        .chain([(20.0, 20.0), (30.0, 20.0)])
+        .collect();
+    let replay = Replay::new(&raw, &table, &profile, &calc, Switching::Midpoint).unwrap();
+    assert_eq!(replay.points.len(), 13);
+    assert_eq!(replay.measured, (0, 10));
+    assert_eq!(replay.points[4].correction, table.eval(4).unwrap());
+
+    let (x, y, both) = replay.stats(1);
+    assert!(x.max_abs <= 1.0);
+    assert_eq!(
+        y,
+        ErrorStats {
+            max_abs: 0.0,
+            rms: 0.0,
+            within: 100.0
+        }
+    );
+    assert_eq!(both, 100.0);
+    assert!((replay.outside() - 50.0).abs() < 1e-9);
+    let (x, _, both) = replay.stats(0);
+    assert!(x.within < 100.0 && both == x.within);
+
+    let mut table = vec![];
+    replay.write_csv(&mut table).unwrap();
+    assert!(String::from_utf8(table)
+        .unwrap()
+        .starts_with("time;temp;dx;dy;error_x;error_y\n0;0;0;0;0.00;0.00\n"));
+    assert!(Replay::new(
+        &DataFrame::default(),
+        &raw,
+        &profile,
+        &calc,
+        Switching::Midpoint
+    )
+    .is_err());
+}
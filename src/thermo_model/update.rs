@@ -0,0 +1,200 @@
+use std::error::Error;
+use std::io::Write;
+
+use super::{Adjustment, CalcOptions, DataFrame, DataRow};
+
+/// Дополнение данных изделия новыми измерениями
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+    /// прежние сырые данные
+    pub old: DataFrame,
+    /// новые сырые данные
+    pub new: DataFrame,
+    /// вес точек прежних сегментов относительно новых, 0..1
+    pub old_weight: f64,
+}
+
+impl Update {
+    pub fn new(old: DataFrame, new: DataFrame, old_weight: f64) -> Result<Self, Box<dyn Error>> {
+        if !(0.0..=1.0).contains(&old_weight) {
+            Err(format!(
+                "Invalid weight of old segments {old_weight}, expected 0..1"
+            ))?
+        }
+        if new.rows.is_empty() {
+            Err("No new data")?
+        }
+        Ok(Self {
+            old,
+            new,
+            old_weight,
+        })
+    }
+
+    /// объединённые сырые данные: строки по возрастанию температуры,
+    /// сегменты прежних данных и за ними сегменты новых
+    pub fn merged(&self) -> DataFrame {
+        let mut item = DataFrame {
+            rows: self.old.rows.clone(),
+            segments: self.old.segments.clone(),
+        };
+        item.rows.extend(self.new.rows.iter().cloned());
+        item.sort();
+        item.segments.extend(self.new.segments.iter().cloned());
+        item
+    }
+
+    /// расчёт модели по объединённым данным, точки прежних сегментов
+    /// входят в оценку узла с весом `old_weight`, и узлы, изменённые ограничениями таблицы
+    pub fn calc(&self, options: &CalcOptions) -> (DataFrame, Vec<Adjustment>) {
+        let mut item = DataFrame::default();
+        let first = self.old.rows.first().or(self.new.rows.first());
+        let (mut past_x, mut past_y) =
+            first.map_or((0, 0), |row| (row.x.unwrap_or(0), row.y.unwrap_or(0)));
+
+        for temp in options.grid() {
+            let mut tail: Vec<(&DataRow, f64)> = self
+                .old
+                .node_rows(temp, options.window)
+                .map(|row| (row, self.old_weight))
+                .chain(
+                    self.new
+                        .node_rows(temp, options.window)
+                        .map(|row| (row, 1.0)),
+                )
+                .collect();
+            // при нулевом весе прежние точки используются только в узлах без новых
+            if tail.iter().all(|(_, weight)| *weight == 0.0) {
+                tail.iter_mut().for_each(|(_, weight)| *weight = 1.0);
+            }
+            tail.retain(|(_, weight)| *weight > 0.0);
+
+            let x = options
+                .estimator
+                .estimate_weighted(tail.iter().map(|(row, w)| (row.x.unwrap(), *w)).collect());
+            let y = options
+                .estimator
+                .estimate_weighted(tail.iter().map(|(row, w)| (row.y.unwrap(), *w)).collect());
+            if let (Some(x), Some(y)) = (x, y) {
+                (past_x, past_y) = (x, y);
+            }
+
+            item.rows.push(DataRow {
+                temp: Some(temp),
+                x: Some(past_x),
+                y: Some(past_y),
+            });
+        }
+        let adjustments = options.constraints.apply(&mut item);
+        (item, adjustments)
+    }
+
+    /// модель `model`, в которой пересчитаны по новым данным только узлы,
+    /// в окне которых есть новые точки, и узлы, изменённые ограничениями таблицы
+    pub fn patch(
+        model: &DataFrame,
+        new: &DataFrame,
+        options: &CalcOptions,
+    ) -> (DataFrame, Vec<Adjustment>) {
+        let mut item = model.clone();
+        for row in item.rows.iter_mut() {
+            let Some(temp) = row.temp else { continue };
+            let tail: Vec<&DataRow> = new.node_rows(temp, options.window).collect();
+
+            let x = options
+                .estimator
+                .estimate(tail.iter().map(|row| row.x.unwrap()).collect());
+            let y = options
+                .estimator
+                .estimate(tail.iter().map(|row| row.y.unwrap()).collect());
+            if let (Some(x), Some(y)) = (x, y) {
+                (row.x, row.y) = (Some(x), Some(y));
+            }
+        }
+        let adjustments = options.constraints.apply(&mut item);
+        (item, adjustments)
+    }
+
+    /// запись объединённых данных в формате `temp;dx;dy` со строками-комментариями сегментов
+    pub fn write_merged<W: Write>(&self, mut writer: W) -> Result<(), Box<dyn Error>> {
+        let mut table = "temp;dx;dy\r\n".to_string();
+        for segment in self.old.segments.iter().chain(self.new.segments.iter()) {
+            table += &format!("# {};;\r\n", segment.comment);
+            for row in segment.rows.iter() {
+                table += &format!("{row}\r\n");
+            }
+        }
+        writer.write_all(table.as_bytes())?;
+        Ok(())
+    }
+
+    /// диапазон температур новых данных
+    pub fn range(&self) -> (i32, i32) {
+        let temps = self.new.rows.iter().filter_map(|row| row.temp);
+        (
+            temps.clone().min().unwrap_or_default(),
+            temps.max().unwrap_or_default(),
+        )
+    }
+}
+
+#[test]
+fn merge_and_patch() {
+    let old: DataFrame = "temp;dx;dy\r\n# plus 01.02;;\r\n0;0;0\r\n6;2;2\r\n12;4;4"
+        .parse()
+        .unwrap();
+    let new: DataFrame = "temp;dx;dy\r\n# plus 03.04;;\r\n11;6;5\r\n12;6;5\r\n13;7;5"
+        .parse()
+        .unwrap();
+    let options = CalcOptions {
+        grid_start: 0,
+        grid_end: 12,
+        grid_step: 6,
+        window: 1,
+        ..Default::default()
+    };
+
+    let update = Update::new(old.clone(), new.clone(), 0.5).unwrap();
+    assert_eq!(update.range(), (11, 13));
+    let merged = update.merged();
+    assert_eq!(merged.rows.len(), 6);
+    assert_eq!(merged.segments.len(), 2);
+    assert_eq!(merged.segments[1].comment, "plus 03.04");
+    let mut table = vec![];
+    update.write_merged(&mut table).unwrap();
+    let table = String::from_utf8(table).unwrap();
+    assert!(table.contains("# plus 01.02;;\r\n0;0;0\r\n"));
+    assert_eq!(table.parse::<DataFrame>().unwrap(), merged);
+
+    let (calc, adjustments) = update.calc(&options);
+    assert!(adjustments.is_empty());
+    assert_eq!(calc.eval(6), Some((2, 2)));
+    assert_eq!(calc.eval(12), Some((6, 5)));
+    let full = Update::new(old.clone(), new.clone(), 1.0).unwrap();
+    assert_eq!(full.calc(&options).0, merged.calc(&options));
+
+    let model = old.calc(&options);
+    let (patched, _) = Update::patch(&model, &new, &options);
+    assert_eq!(patched.eval(0), Some((0, 0)));
+    assert_eq!(patched.eval(6), Some((2, 2)));
+    assert_eq!(patched.eval(12), Some((6, 5)));
+
+    // ограничения действуют и на дополненную, и на исправленную модель
+    let limited = CalcOptions {
+        constraints: super::Constraints {
+            max: (Some(4), None),
+            max_step: Some(3),
+            ..Default::default()
+        },
+        ..options
+    };
+    let (calc, adjustments) = update.calc(&limited);
+    assert_eq!(calc.eval(12), Some((4, 5)));
+    assert_eq!(adjustments[0].to_string(), "12, dx 6 → 4: max");
+    let (patched, adjustments) = Update::patch(&model, &new, &limited);
+    assert_eq!(patched.eval(12), Some((4, 5)));
+    assert_eq!(adjustments.len(), 1);
+
+    assert!(Update::new(old.clone(), new, 2.0).is_err());
+    assert!(Update::new(old, DataFrame::default(), 0.5).is_err());
+}
@@ -0,0 +1,504 @@
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{self, ClearType},
+};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::Write;
+
+use r_approx::{braille, CalcOptions, DataFrame, DataRow, Override, Switching};
+
+/// ширина таблицы узлов слева от графиков
+const TABLE_WIDTH: usize = 36;
+const HELP: &str = "←/→ node  ↑/↓ dx  PgUp/PgDn dy  [ ] point  space exclude  s save  q quit";
+
+/// Просмотр и правка модели: узлы, исключённые точки и ручные значения
+#[derive(Debug, Clone)]
+pub struct Review {
+    title: String,
+    raw: DataFrame,
+    /// исходная модель
+    base: DataFrame,
+    options: CalcOptions,
+    /// индексы исключённых строк сырых данных
+    excluded: Vec<usize>,
+    /// ручные значения узлов по температуре
+    edits: BTreeMap<i32, (i32, i32)>,
+    /// модель с учётом исключённых точек без ручных значений
+    calc: DataFrame,
+    node: usize,
+    point: usize,
+    modified: bool,
+}
+
+impl Review {
+    pub fn new(
+        title: &str,
+        raw: DataFrame,
+        base: DataFrame,
+        options: CalcOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        if base.rows.iter().any(|row| row.temp.is_none()) || base.rows.is_empty() {
+            Err("Empty or invalid model")?
+        }
+        Ok(Self {
+            title: title.to_string(),
+            calc: base.clone(),
+            raw,
+            base,
+            options,
+            excluded: vec![],
+            edits: BTreeMap::new(),
+            node: 0,
+            point: 0,
+            modified: false,
+        })
+    }
+
+    /// есть ли несохранённые изменения
+    pub fn modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn saved(&mut self) {
+        self.modified = false;
+    }
+
+    /// сырые данные без исключённых точек
+    pub fn kept(&self) -> DataFrame {
+        let excluded: Vec<&DataRow> = self.excluded.iter().map(|i| &self.raw.rows[*i]).collect();
+        let mut item = self.raw.clone();
+        item.rows = self
+            .raw
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.excluded.contains(i))
+            .map(|(_, row)| row.clone())
+            .collect();
+        for segment in item.segments.iter_mut() {
+            segment.rows.retain(|row| !excluded.contains(&row));
+        }
+        item
+    }
+
+    /// модель с учётом исключённых точек без ручных значений
+    pub fn calc_data(&self) -> &DataFrame {
+        &self.calc
+    }
+
+    /// модель с ручными значениями
+    pub fn model(&self) -> DataFrame {
+        let mut model = self.calc.clone();
+        for row in model.rows.iter_mut() {
+            if let Some((x, y)) = row.temp.and_then(|temp| self.edits.get(&temp)) {
+                (row.x, row.y) = (Some(*x), Some(*y));
+            }
+        }
+        model
+    }
+
+    /// ручные значения узлов как правки с автором и причиной
+    pub fn overrides(&self, author: &str, reason: &str) -> Vec<Override> {
+        self.edits
+            .iter()
+            .filter_map(|(temp, (x, y))| {
+                let calc = self.calc.eval(*temp)?;
+                (calc != (*x, *y)).then(|| Override {
+                    temp: *temp,
+                    x: (calc.0 != *x).then_some(*x),
+                    y: (calc.1 != *y).then_some(*y),
+                    author: author.to_string(),
+                    reason: reason.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// замечание о просмотре со списком исключённых точек
+    pub fn note(&self, author: &str, text: &str) -> String {
+        let mut note = format!("review by {author}: {text}");
+        if !self.excluded.is_empty() {
+            let points: Vec<String> = self
+                .excluded
+                .iter()
+                .map(|i| {
+                    let row = &self.raw.rows[*i];
+                    format!(
+                        "{} ({}, {})",
+                        row.temp.unwrap_or(0),
+                        row.x.unwrap_or(0),
+                        row.y.unwrap_or(0)
+                    )
+                })
+                .collect();
+            note += &format!(", excluded points: {}", points.join(", "));
+        }
+        note
+    }
+
+    fn temp(&self) -> i32 {
+        self.calc.rows[self.node].temp.unwrap_or(0)
+    }
+
+    fn in_window(&self, row: &DataRow, temp: i32) -> bool {
+        row.temp.map_or(false, |row_temp| {
+            (row_temp - temp).abs() <= self.options.window
+        })
+    }
+
+    /// индексы строк сырых данных в окне текущего узла по возрастанию температуры
+    fn node_points(&self) -> Vec<usize> {
+        let temp = self.temp();
+        let mut points: Vec<usize> = (0..self.raw.rows.len())
+            .filter(|i| self.in_window(&self.raw.rows[*i], temp))
+            .collect();
+        points.sort_by_key(|i| self.raw.rows[*i].temp);
+        points
+    }
+
+    /// число оставшихся точек в окне каждого узла
+    pub fn counts(&self) -> Vec<usize> {
+        let kept = self.kept();
+        self.calc
+            .rows
+            .iter()
+            .map(|node| {
+                let temp = node.temp.unwrap_or(0);
+                kept.rows
+                    .iter()
+                    .filter(|row| self.in_window(row, temp))
+                    .count()
+            })
+            .collect()
+    }
+
+    /// наибольшие по модулю отклонения оставшихся точек в окне каждого узла по осям x и y
+    pub fn residuals(&self) -> Vec<(Option<i32>, Option<i32>)> {
+        let residuals = self.kept().residuals(&self.model());
+        self.calc
+            .rows
+            .iter()
+            .map(|node| {
+                let temp = node.temp.unwrap_or(0);
+                let rows = residuals
+                    .rows
+                    .iter()
+                    .filter(|row| self.in_window(row, temp));
+                let max = |value: fn(&DataRow) -> Option<i32>| {
+                    rows.clone().filter_map(value).map(|v| v.abs()).max()
+                };
+                (max(|row| row.x), max(|row| row.y))
+            })
+            .collect()
+    }
+
+    pub fn select_node(&mut self, delta: isize) {
+        let last = self.calc.rows.len() as isize - 1;
+        self.node = (self.node as isize + delta).clamp(0, last) as usize;
+        self.point = 0;
+    }
+
+    pub fn select_point(&mut self, delta: isize) {
+        let count = self.node_points().len() as isize;
+        if count > 0 {
+            self.point = (self.point as isize + delta).rem_euclid(count) as usize;
+        }
+    }
+
+    /// исключение выбранной точки или её возврат, узлы с этой точкой пересчитываются
+    pub fn toggle_point(&mut self) {
+        let Some(index) = self.node_points().get(self.point).copied() else {
+            return;
+        };
+        match self.excluded.iter().position(|i| *i == index) {
+            Some(position) => {
+                self.excluded.remove(position);
+            }
+            None => self.excluded.push(index),
+        }
+        self.recalc();
+        self.modified = true;
+    }
+
+    /// изменение значения текущего узла на `delta` по осям x и y
+    pub fn adjust(&mut self, delta: (i32, i32)) {
+        let temp = self.temp();
+        let Some((x, y)) = self.model().eval(temp) else {
+            return;
+        };
+        let value = (x + delta.0, y + delta.1);
+        if self.calc.eval(temp) == Some(value) {
+            self.edits.remove(&temp);
+        } else {
+            self.edits.insert(temp, value);
+        }
+        self.modified = true;
+    }
+
+    /// пересчёт узлов, в окне которых есть исключённые точки; узел без оставшихся
+    /// точек сохраняет значение исходной модели
+    fn recalc(&mut self) {
+        let kept = self.kept();
+        let fitted = (!kept.rows.is_empty()).then(|| kept.calc(&self.options));
+        let counts = self.counts();
+
+        let mut calc = self.base.clone();
+        for (node, count) in calc.rows.iter_mut().zip(counts) {
+            let temp = node.temp.unwrap_or(0);
+            let affected = self
+                .excluded
+                .iter()
+                .any(|i| self.in_window(&self.raw.rows[*i], temp));
+            if !affected || count == 0 {
+                continue;
+            }
+            if let Some((x, y)) = fitted.as_ref().and_then(|fitted| fitted.eval(temp)) {
+                (node.x, node.y) = (Some(x), Some(y));
+            }
+        }
+        self.calc = calc;
+    }
+
+    /// строки экрана размером `columns`×`rows` со строкой состояния `status`
+    pub fn render(&self, columns: usize, rows: usize, status: &str) -> Vec<String> {
+        let body = rows.saturating_sub(3);
+        let model = self.model();
+        let counts = self.counts();
+        let residuals = self.residuals();
+        let temp = self.temp();
+
+        let mut table = vec![format!(
+            "  {:>4} {:>5} {:>5} {:>4} {:>4} {:>4}",
+            "temp", "dx", "dy", "n", "|rx|", "|ry|"
+        )];
+        let visible = body.saturating_sub(1).max(1);
+        let start = self
+            .node
+            .saturating_sub(visible / 2)
+            .min(model.rows.len().saturating_sub(visible));
+        for (i, row) in model.rows.iter().enumerate().skip(start).take(visible) {
+            let edited = row
+                .temp
+                .map_or(false, |temp| self.edits.contains_key(&temp));
+            let residual = |value: Option<i32>| value.map_or("-".to_string(), |v| v.to_string());
+            table.push(format!(
+                "{} {:>4} {:>5} {:>5} {:>4} {:>4} {:>4}",
+                if i == self.node { '>' } else { ' ' },
+                row.temp.unwrap_or(0),
+                format!("{}{}", row.x.unwrap_or(0), if edited { "*" } else { "" }),
+                format!("{}{}", row.y.unwrap_or(0), if edited { "*" } else { "" }),
+                counts[i],
+                residual(residuals[i].0),
+                residual(residuals[i].1),
+            ));
+        }
+
+        let width = columns.saturating_sub(TABLE_WIDTH);
+        let height = body / 2;
+        let kept = self.kept();
+        let mut panels = vec![];
+        for (name, value) in [
+            ("dx", (|row: &DataRow| row.x) as fn(&DataRow) -> Option<i32>),
+            ("dy", |row: &DataRow| row.y),
+        ] {
+            let points = |frame: &DataFrame| -> Vec<(i32, i32)> {
+                frame
+                    .rows
+                    .iter()
+                    .filter_map(|row| Some((row.temp?, value(row)?)))
+                    .collect()
+            };
+            panels.push(name.to_string());
+            panels.extend(braille::chart(
+                &points(&kept),
+                &points(&model),
+                width,
+                height.saturating_sub(2).max(1),
+                Some(temp),
+                // отклонения при просмотре считаются с переключением посередине
+                Switching::Midpoint,
+            ));
+        }
+
+        let mut lines = vec![format!("{}  node {temp}", self.title), HELP.to_string()];
+        for i in 0..body {
+            let left = table.get(i).map_or("", |line| line.as_str());
+            let right = panels.get(i).map_or("", |line| line.as_str());
+            lines.push(format!("{left:<width$}{right}", width = TABLE_WIDTH));
+        }
+
+        let point = self.node_points().get(self.point).map(|i| {
+            let row = &self.raw.rows[*i];
+            format!(
+                "point {}/{}: {}; {}; {}{}",
+                self.point + 1,
+                self.node_points().len(),
+                row.temp.unwrap_or(0),
+                row.x.unwrap_or(0),
+                row.y.unwrap_or(0),
+                if self.excluded.contains(i) {
+                    " excluded"
+                } else {
+                    ""
+                }
+            )
+        });
+        lines.push(format!("{}  {status}", point.unwrap_or_default()));
+
+        lines
+            .into_iter()
+            .map(|line| line.chars().take(columns).collect())
+            .collect()
+    }
+}
+
+/// интерактивный просмотр, `save` сохраняет результат с замечанием и возвращает сообщение
+pub fn run<F>(mut review: Review, mut save: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&Review, &str) -> Result<String, Box<dyn Error>>,
+{
+    let mut stdout = std::io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    let result = event_loop(&mut review, &mut save, &mut stdout);
+
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn event_loop<F, W>(review: &mut Review, save: &mut F, out: &mut W) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&Review, &str) -> Result<String, Box<dyn Error>>,
+    W: Write,
+{
+    let mut status = String::default();
+    let mut note: Option<String> = None;
+    let mut quit = false;
+
+    loop {
+        let (columns, rows) = terminal::size()?;
+        let status_line = match &note {
+            Some(text) => format!("audit note: {text}_"),
+            None => status.clone(),
+        };
+        for (i, line) in review
+            .render(columns as usize, rows as usize, &status_line)
+            .iter()
+            .enumerate()
+        {
+            queue!(
+                out,
+                cursor::MoveTo(0, i as u16),
+                terminal::Clear(ClearType::CurrentLine),
+                Print(line)
+            )?;
+        }
+        out.flush()?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Ok(());
+        }
+
+        if let Some(text) = note.as_mut() {
+            match key.code {
+                KeyCode::Enter => {
+                    let text = note.take().unwrap_or_default();
+                    status = match save(review, &text) {
+                        Ok(message) => {
+                            review.saved();
+                            message
+                        }
+                        Err(err) => format!("error: {err}"),
+                    };
+                }
+                KeyCode::Esc => {
+                    note = None;
+                    status.clear();
+                }
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) => text.push(c),
+                _ => {}
+            }
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Left => review.select_node(-1),
+            KeyCode::Right => review.select_node(1),
+            KeyCode::Up => review.adjust((1, 0)),
+            KeyCode::Down => review.adjust((-1, 0)),
+            KeyCode::PageUp => review.adjust((0, 1)),
+            KeyCode::PageDown => review.adjust((0, -1)),
+            KeyCode::Char('[') => review.select_point(-1),
+            KeyCode::Char(']') => review.select_point(1),
+            KeyCode::Char(' ') => review.toggle_point(),
+            KeyCode::Char('s') => note = Some(String::default()),
+            KeyCode::Char('q') | KeyCode::Esc => {
+                if !review.modified() || quit {
+                    return Ok(());
+                }
+                status = "unsaved changes, press q again to quit".to_string();
+                quit = true;
+                continue;
+            }
+            _ => {}
+        }
+        quit = false;
+    }
+}
+
+#[test]
+fn review_edits() {
+    let raw: DataFrame = "temp;dx;dy\r\n-1;1;0\r\n0;1;0\r\n1;9;0\r\n5;2;1\r\n6;2;1"
+        .parse()
+        .unwrap();
+    let options = CalcOptions {
+        grid_start: 0,
+        grid_end: 6,
+        grid_step: 6,
+        window: 1,
+        ..Default::default()
+    };
+    let mut review = Review::new("data.csv", raw.clone(), raw.calc(&options), options).unwrap();
+    assert_eq!(review.model().eval(0), Some((1, 0)));
+    assert_eq!(review.counts(), vec![3, 2]);
+    assert_eq!(review.residuals()[0], (Some(8), Some(0)));
+
+    review.select_point(-1);
+    review.toggle_point();
+    assert!(review.modified());
+    assert_eq!(review.kept().rows.len(), 4);
+    assert_eq!(review.counts(), vec![2, 2]);
+    assert_eq!(review.residuals()[0], (Some(0), Some(0)));
+
+    review.select_node(5);
+    review.adjust((0, 1));
+    review.adjust((1, 0));
+    assert_eq!(review.model().eval(6), Some((3, 2)));
+    let overrides = review.overrides("Иванов", "ступенька");
+    assert_eq!(overrides.len(), 1);
+    assert_eq!((overrides[0].temp, overrides[0].x), (6, Some(3)));
+    review.adjust((-1, -1));
+    assert!(review.overrides("Иванов", "ступенька").is_empty());
+
+    assert_eq!(
+        review.note("Иванов", "выброс"),
+        "review by Иванов: выброс, excluded points: 1 (9, 0)"
+    );
+
+    let lines = review.render(80, 20, "");
+    assert_eq!(lines.len(), 20);
+    assert!(lines[2].contains("temp"));
+    assert!(lines[4].starts_with(">    6"));
+}
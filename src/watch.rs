@@ -0,0 +1,83 @@
+//! Отслеживание изменений файлов с сырыми данными
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::batch;
+use crate::thermo_model::NameTemplates;
+
+/// время изменения и размер файла
+type Signature = (Option<SystemTime>, u64);
+
+/// Отслеживание файла или папки с сырыми данными по времени изменения и размеру файлов
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    path: PathBuf,
+    names: NameTemplates,
+    files: BTreeMap<PathBuf, Signature>,
+}
+
+impl Watcher {
+    /// `path` — файл с сырыми данными или папка, в которой ищутся такие файлы,
+    /// файлы моделей по шаблонам `names` пропускаются
+    pub fn new<P: AsRef<Path>>(path: P, names: NameTemplates) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            names,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// файлы, появившиеся или изменившиеся с прошлой проверки, при первой проверке все файлы;
+    /// ещё не созданный файл пропускается
+    pub fn changed(&mut self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let files = if self.path.is_dir() {
+            batch::find_data_files(&self.path, &self.names)?
+        } else {
+            vec![self.path.clone()]
+        };
+
+        let mut changed = vec![];
+        let mut current = BTreeMap::new();
+        for path in files {
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                continue;
+            };
+            let signature = (metadata.modified().ok(), metadata.len());
+            if self.files.get(&path) != Some(&signature) {
+                changed.push(path.clone());
+            }
+            current.insert(path, signature);
+        }
+        self.files = current;
+        Ok(changed)
+    }
+}
+
+#[test]
+fn watch_changes() {
+    let root = std::env::temp_dir().join(format!("r-approx-watch-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    let path = root.join("data.csv");
+
+    let mut file = Watcher::new(&path, NameTemplates::default());
+    assert!(file.changed().unwrap().is_empty());
+    std::fs::write(&path, "temp;dx;dy\r\n0;0;0\r\n").unwrap();
+    assert_eq!(file.changed().unwrap(), vec![path.clone()]);
+    assert!(file.changed().unwrap().is_empty());
+
+    let mut folder = Watcher::new(&root, NameTemplates::default());
+    assert_eq!(folder.changed().unwrap(), vec![path.clone()]);
+    std::fs::write(root.join("data_auto_model.txt"), "temp;dx;dy\r\n0;0;0\r\n").unwrap();
+    assert!(folder.changed().unwrap().is_empty());
+
+    let mut table = std::fs::read_to_string(&path).unwrap();
+    table += "6;1;1\r\n";
+    std::fs::write(&path, table).unwrap();
+    assert_eq!(file.changed().unwrap(), vec![path.clone()]);
+    assert_eq!(folder.changed().unwrap(), vec![path]);
+    std::fs::remove_dir_all(&root).unwrap();
+}